@@ -1,6 +1,6 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 
-use gchemol_neighbors::Neighborhood;
+use gchemol_neighbors::{Backend, Neighborhood};
 fn setup() -> Neighborhood {
     let particles = [[ 0.60421912,  4.2840792 ,  0.67433509],
                      [-0.69258171,  3.9731936 ,  3.49208748],
@@ -49,5 +49,50 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn read_points_xyz(txt: &str) -> Vec<[f64; 3]> {
+    let mut positions = Vec::new();
+    for line in txt.lines().skip(2) {
+        let attrs: Vec<_> = line.split_whitespace().collect();
+        let (_symbol, position) = attrs.split_first().expect("empty line");
+        let p: Vec<f64> = position.iter().map(|x| x.parse().unwrap()).collect();
+        positions.push([p[0], p[1], p[2]]);
+    }
+
+    positions
+}
+
+// Zeolite CLO, containing 4704 atoms
+fn setup_clo(backend: Backend) -> Neighborhood {
+    let stream = include_str!("../data/25/87bf60-cbe7-44bc-a2f0-c369b329f968/CLO.xyz");
+    let particles = read_points_xyz(stream);
+    let a = 51.712;
+    let cell = [[a, 0.0, 0.0], [0.0, a, 0.0], [0.0, 0.0, a]];
+
+    let mut nh = Neighborhood::new();
+    nh.set_backend(backend);
+    nh.update(particles.iter().enumerate().map(|(i, &v)| (i, v)));
+    nh.set_lattice(cell);
+    nh
+}
+
+fn backend_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("CLO");
+    group.sample_size(10);
+    for backend in [Backend::Octree, Backend::KdTree, Backend::CellList] {
+        let nh = setup_clo(backend);
+        for cutoff in [2.0, 4.0] {
+            let id = BenchmarkId::new(format!("{backend:?}"), cutoff);
+            group.bench_with_input(id, &cutoff, |b, &cutoff| {
+                b.iter(|| {
+                    for i in 0..nh.npoints() {
+                        let _nodes: Vec<_> = nh.neighbors(i, cutoff).collect();
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, backend_benchmark);
 criterion_main!(benches);
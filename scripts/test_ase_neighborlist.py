@@ -27,3 +27,52 @@ def test_nl(atoms, cutoff):
         (x, _) = nl.get_neighbors(i)
         # break
         # return sorted(x)
+
+# Reference neighbor lists for tests/images.rs
+#
+# Write all neighbor pairs (both ways, without self interaction) found by
+# ASE, for a primitive fcc cell and a strongly skewed cell, with cutoffs
+# larger than the cell. Usage: python3 scripts/test_ase_neighborlist.py
+SKEWED_CELL = [[ 8.60700000e+00,  0.00000000e+00,  0.00000000e+00],
+               [ 8.64636107e-04,  4.95399992e+00,  0.00000000e+00],
+               [-3.14318359e+00,  1.38078488e-02,  6.91625732e+00]]
+
+# fractional coordinates, some of them outside the unit cell
+SKEWED_FRACS = [[ 0.10,  0.20,  0.30],
+                [ 0.85,  0.40,  0.05],
+                [ 0.50,  0.95,  0.60],
+                [-0.30,  0.10,  0.90],
+                [ 1.25,  0.70,  0.40],
+                [ 0.40, -0.45,  0.15],
+                [ 0.70,  0.55,  1.35],
+                [ 0.05,  0.90, -0.20]]
+
+FCC_A = 3.61
+FCC_CELL = [[0.0, FCC_A/2, FCC_A/2], [FCC_A/2, 0.0, FCC_A/2], [FCC_A/2, FCC_A/2, 0.0]]
+
+def write_reference(filename, cell, fracs, cutoff):
+    cell = np.array(cell)
+    positions = np.array(fracs) @ cell
+    atoms = ase.Atoms(positions=positions, cell=cell, pbc=True)
+    nl = NeighborList([cutoff/2]*len(atoms), skin=0.0, self_interaction=False, bothways=True, primitive=NewPrimitiveNeighborList)
+    nl.update(atoms)
+    pairs = []
+    for i in range(len(atoms)):
+        js, offsets = nl.get_neighbors(i)
+        for j, o in zip(js, offsets):
+            d = np.linalg.norm(positions[j] + o @ cell - positions[i])
+            pairs.append((i, int(j), *[int(x) for x in o], float(d)))
+
+    with open(filename, "w") as fp:
+        fp.write("# generated by scripts/test_ase_neighborlist.py using ase %s NeighborList\n" % ase.__version__)
+        fp.write("cutoff %r\n" % cutoff)
+        for v in cell:
+            fp.write("cell %r %r %r\n" % tuple(v))
+        for i, p in enumerate(positions):
+            fp.write("point %d %r %r %r\n" % (i, *p))
+        for i, j, ia, ib, ic, d in sorted(pairs):
+            fp.write("pair %d %d %d %d %d %.10f\n" % (i, j, ia, ib, ic, d))
+
+if __name__ == "__main__":
+    write_reference("tests/files/ase-fcc-pairs.txt", FCC_CELL, [[0.3, -1.2, 2.1]], 6.0)
+    write_reference("tests/files/ase-skewed-pairs.txt", SKEWED_CELL, SKEWED_FRACS, 6.0)
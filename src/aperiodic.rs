@@ -2,28 +2,31 @@
 
 // [[file:~/Workspace/Programming/gchemol-rs/neighbors/neighbors.note::*imports][imports:1]]
 use crate::base::*;
+use vecfx::Vector3f;
 // imports:1 ends here
 
 // core
 
 // [[file:~/Workspace/Programming/gchemol-rs/neighbors/neighbors.note::*core][core:1]]
-impl Neighborhood {
+impl<K: Key> Neighborhood<K> {
     pub(crate) fn search_neighbors_aperiodic(
         &self,
         pt: Point,
+        rmin: f64,
         radius: f64,
-    ) -> impl Iterator<Item = Neighbor> + '_ {
-        self.tree
+    ) -> impl Iterator<Item = Neighbor<K>> + '_ {
+        self.index
             .as_ref()
             .expect("octree not ready")
-            .search(pt, radius)
-            .into_iter()
-            .map(move |(index, distance)| {
-                let (&node, _) = self.points.get_index(index).expect("invalid index");
+            .search_shell(&self.points, pt, rmin, radius)
+            .map(move |(index, distance, _)| {
+                let (node, &position) = self.points.get_index(index).expect("invalid index");
                 Neighbor {
-                    node,
+                    node: node.clone(),
                     distance,
                     image: None,
+                    vector: Vector3f::from(position) - Vector3f::from(pt),
+                    position,
                 }
             })
     }
@@ -0,0 +1,210 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use crate::cells::CellList;
+use crate::image::Image;
+use crate::kdtree::KdTree;
+use lattice::Lattice;
+use octree::Octree;
+use std::sync::Arc;
+use vecfx::Vector3f;
+// import:1 ends here
+
+// [[file:../neighbors.note::d2a7f0c3][d2a7f0c3]]
+/// Common interface of spatial index over a static point set.
+pub(crate) trait SpatialIndex: std::fmt::Debug + Send + Sync {
+    /// Return indices of points within distance `radius` of `pt`, together
+    /// with their distances.
+    fn search(&self, pt: Point, radius: f64) -> Vec<(usize, f64)>;
+
+    /// Return indices of points with distances to `pt` in range `rmin..rmax`,
+    /// together with their distances. Regions inside the inner sphere are
+    /// skipped if the index supports it.
+    fn search_shell(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64)>;
+
+    /// Return indices of at most `k` nearest points to `pt`, together with
+    /// their distances, sorted by distance. Periodic images are not
+    /// considered.
+    fn nearest(&self, pt: Point, k: usize) -> Vec<(usize, f64)>;
+
+    /// Return the index as `PeriodicIndex` if periodic images are handled
+    /// natively, or None if images have to be found by replicating the
+    /// query point.
+    fn as_periodic(&self) -> Option<&dyn PeriodicIndex> {
+        None
+    }
+}
+
+/// Spatial index searching periodic images natively.
+pub(crate) trait PeriodicIndex: SpatialIndex {
+    /// Return indices of points with distances to `pt` in range
+    /// `rmin..rmax`, together with their distances and images, for periodic
+    /// system.
+    fn search_periodic(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64, Image)>;
+}
+
+/// Spatial index algorithm for neighbors search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Octree in Cartesian space. Periodic images are found by
+    /// replicating the query point. Known limitation: regions inside the
+    /// inner sphere of shell queries are not skipped, but filtered out after
+    /// searching the outer sphere.
+    #[default]
+    Octree,
+
+    /// k-d tree in Cartesian space. Periodic images are found by
+    /// replicating the query point. Regions inside the inner sphere of
+    /// shell queries are skipped.
+    KdTree,
+
+    /// Linked-cell binning in fractional coordinates. Only used for
+    /// periodic system, and octree is used for aperiodic system.
+    CellList,
+
+    /// Check all points one by one. Slow, but useful as a reference
+    /// implementation.
+    BruteForce,
+}
+
+impl Backend {
+    /// Build spatial index for `points`, with periodic boundary conditions
+    /// `pbc` along lattice vectors.
+    pub(crate) fn build(self, points: &[Point], lattice: Option<Lattice>, pbc: [bool; 3]) -> Arc<dyn SpatialIndex> {
+        match (self, lattice) {
+            (Backend::Octree, _) | (Backend::CellList, None) => Arc::new(OctreeIndex::new(points)),
+            (Backend::CellList, Some(lattice)) => Arc::new(CellList::build(points, lattice, pbc)),
+            (Backend::KdTree, _) => Arc::new(KdTree::new(points)),
+            (Backend::BruteForce, _) => Arc::new(BruteForce::new(points)),
+        }
+    }
+
+    /// Return true if the spatial index depends on lattice.
+    pub(crate) fn uses_lattice(self) -> bool {
+        self == Backend::CellList
+    }
+}
+// d2a7f0c3 ends here
+
+// [[file:../neighbors.note::*octree][octree:1]]
+/// The max number of points each octant holds.
+const BUCKET_SIZE: usize = 100;
+
+/// Octree backend.
+#[derive(Debug, Clone)]
+struct OctreeIndex {
+    tree: Octree,
+    /// The lower and upper corners of bounding box of all points.
+    bounds: [Point; 2],
+}
+
+impl OctreeIndex {
+    fn new(points: &[Point]) -> Self {
+        let mut tree = Octree::new(points.iter().copied());
+        tree.build(BUCKET_SIZE);
+        let bounds = bounding_box(points);
+        Self { tree, bounds }
+    }
+}
+
+impl SpatialIndex for OctreeIndex {
+    fn search(&self, pt: Point, radius: f64) -> Vec<(usize, f64)> {
+        self.tree.search(pt, radius).collect()
+    }
+
+    // NOTE: octants are not exposed by octree crate, so we simply filter out
+    // points inside the inner sphere.
+    fn search_shell(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64)> {
+        self.tree.search(pt, rmax).filter(|&(_, d)| d >= rmin).collect()
+    }
+
+    fn nearest(&self, pt: Point, k: usize) -> Vec<(usize, f64)> {
+        nearest_by_search(self, pt, k, self.tree.points.len(), self.bounds)
+    }
+}
+
+/// Return the lower and upper corners of bounding box of `points`.
+pub(crate) fn bounding_box(points: &[Point]) -> [Point; 2] {
+    let mut lo = [f64::INFINITY; 3];
+    let mut hi = [f64::NEG_INFINITY; 3];
+    for p in points {
+        for i in 0..3 {
+            lo[i] = lo[i].min(p[i]);
+            hi[i] = hi[i].max(p[i]);
+        }
+    }
+    [lo, hi]
+}
+
+/// k nearest neighbors search by range queries with increasing radius.
+pub(crate) fn nearest_by_search(
+    index: &dyn SpatialIndex,
+    pt: Point,
+    k: usize,
+    npoints: usize,
+    bounds: [Point; 2],
+) -> Vec<(usize, f64)> {
+    let k = k.min(npoints);
+    if k == 0 {
+        return vec![];
+    }
+
+    // all points will be found within this distance
+    let [lo, hi] = bounds;
+    let rmax = (0..3)
+        .map(|i| (pt[i] - lo[i]).abs().max((pt[i] - hi[i]).abs()).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    // initial guess from average density of points, which should be
+    // positive for points coincident with `pt`
+    let volume: f64 = (0..3).map(|i| (hi[i] - lo[i]).max(1.0)).product();
+    let mut r = (volume * k as f64 / npoints as f64).cbrt().min(rmax).max(1e-8);
+    loop {
+        let mut found = index.search(pt, r);
+        if found.len() >= k || r > rmax {
+            found.sort_by(|a, b| a.1.total_cmp(&b.1));
+            found.truncate(k);
+            return found;
+        }
+        r *= 2.0;
+    }
+}
+// octree:1 ends here
+
+// [[file:../neighbors.note::*brute force][brute force:1]]
+/// Brute force backend checking all points.
+#[derive(Debug, Clone)]
+struct BruteForce {
+    points: Vec<Point>,
+}
+
+impl BruteForce {
+    fn new(points: &[Point]) -> Self {
+        Self { points: points.to_vec() }
+    }
+
+    fn distances(&self, pt: Point) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let pt = Vector3f::from(pt);
+        self.points
+            .iter()
+            .enumerate()
+            .map(move |(i, &p)| (i, (Vector3f::from(p) - pt).norm()))
+    }
+}
+
+impl SpatialIndex for BruteForce {
+    fn search(&self, pt: Point, radius: f64) -> Vec<(usize, f64)> {
+        self.distances(pt).filter(|&(_, d)| d < radius).collect()
+    }
+
+    fn search_shell(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64)> {
+        self.distances(pt).filter(|&(_, d)| d < rmax && d >= rmin).collect()
+    }
+
+    fn nearest(&self, pt: Point, k: usize) -> Vec<(usize, f64)> {
+        let mut found: Vec<_> = self.distances(pt).collect();
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found.truncate(k);
+        found
+    }
+}
+// brute force:1 ends here
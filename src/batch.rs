@@ -0,0 +1,39 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use crate::periodic::ImageRanges;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::e2b84c19][e2b84c19]]
+impl<K: Key> Neighborhood<K> {
+    /// Return neighbors of many particles `pts` within distance `radius`,
+    /// grouped by query point in the same order as `pts`.
+    ///
+    /// This is the same as calling `search` for each point, but the setup
+    /// shared by all queries is only done once. Queries run in parallel if
+    /// the `rayon` feature is enabled.
+    pub fn search_many(&self, pts: &[Point], radius: f64) -> Vec<Vec<Neighbor<K>>> {
+        if self.points.is_empty() {
+            return vec![vec![]; pts.len()];
+        }
+
+        let index = self.index.as_ref().expect("octree not ready");
+        let images = self.lattice.filter(|_| !index.is_periodic()).map(|lattice| ImageRanges::new(self, lattice));
+        let search = |&pt: &Point| -> Vec<Neighbor<K>> {
+            match images {
+                Some(images) => self.search_neighbors_images(pt, 0.0, radius, images).collect(),
+                None => self.search(pt, radius).collect(),
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        let found = pts.par_iter().map(search).collect();
+        #[cfg(not(feature = "rayon"))]
+        let found = pts.iter().map(search).collect();
+
+        found
+    }
+}
+// e2b84c19 ends here
@@ -0,0 +1,141 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use crate::elements::*;
+use crate::error::*;
+use indexmap::IndexMap;
+use std::borrow::Borrow;
+// import:1 ends here
+
+// [[file:../neighbors.note::c8e04b1f][c8e04b1f]]
+/// Atomic radii used for bond perception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Radii {
+    /// Covalent radii from Cordero et al. Elements without data use 1.5
+    /// Angstrom.
+    #[default]
+    Covalent,
+
+    /// van der Waals radii from Bondi and Mantina et al. Elements without
+    /// data use 2.0 Angstrom.
+    VanDerWaals,
+}
+
+impl Radii {
+    fn radius(self, z: usize) -> f64 {
+        match self {
+            Radii::Covalent => covalent_radius(z).unwrap_or(1.5),
+            Radii::VanDerWaals => vdw_radius(z).unwrap_or(2.0),
+        }
+    }
+}
+
+/// Element-aware neighborhood for bond perception.
+///
+/// Two atoms are bonded if their distance is less than the sum of their
+/// atomic radii plus a tolerance.
+#[derive(Debug, Clone)]
+pub struct Bonding<K = usize> {
+    nh: Neighborhood<K>,
+    /// atomic number of each atom
+    elements: IndexMap<K, usize>,
+    radii: Radii,
+    tolerance: f64,
+}
+
+impl<K> Default for Bonding<K> {
+    fn default() -> Self {
+        Self {
+            nh: Neighborhood::default(),
+            elements: IndexMap::new(),
+            radii: Radii::default(),
+            tolerance: 0.45,
+        }
+    }
+}
+
+impl<K: Key> Bonding<K> {
+    /// Constructs with covalent radii and a tolerance of 0.45 Angstrom.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update atoms from an iterator of key, element (atomic number or
+    /// symbol) and position. Return an error for invalid element without
+    /// changing anything.
+    pub fn update<I, E>(&mut self, iter: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, E, Point)>,
+        E: AsElement + std::fmt::Debug,
+    {
+        let mut atoms = vec![];
+        for (k, e, p) in iter {
+            let z = e.atomic_number().ok_or_else(|| Error::InvalidElement(format!("{e:?}")))?;
+            check_point(p)?;
+            atoms.push((k, z, p));
+        }
+
+        self.nh.update(atoms.iter().map(|(k, _, p)| (k.clone(), *p)));
+        for (k, z, _) in atoms.iter() {
+            self.elements.insert(k.clone(), *z);
+        }
+        self.update_cutoffs(atoms.into_iter().map(|(k, z, _)| (k, z)));
+        Ok(())
+    }
+
+    fn update_cutoffs(&mut self, iter: impl Iterator<Item = (K, usize)>) {
+        let radii = self.radii;
+        self.nh.set_cutoffs(iter.map(|(k, z)| (k, radii.radius(z))));
+    }
+
+    /// Set lattice for applying periodic boundary conditions.
+    pub fn set_lattice(&mut self, mat: [[f64; 3]; 3]) {
+        self.nh.set_lattice(mat);
+    }
+
+    /// Remove the lattice, going back to aperiodic system.
+    pub fn remove_lattice(&mut self) {
+        self.nh.remove_lattice();
+    }
+
+    /// Set periodic boundary conditions along three cell directions.
+    pub fn set_periodicity(&mut self, pbc: [bool; 3]) {
+        self.nh.set_periodicity(pbc);
+    }
+
+    /// Set the kind of atomic radii. The default is covalent radii.
+    pub fn set_radii(&mut self, radii: Radii) {
+        self.radii = radii;
+        let elements: Vec<_> = self.elements.iter().map(|(k, &z)| (k.clone(), z)).collect();
+        self.update_cutoffs(elements.into_iter());
+    }
+
+    /// Set the tolerance added to the sum of atomic radii.
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        self.tolerance = tolerance;
+    }
+
+    /// Return the atomic number of atom `n`.
+    pub fn get_element(&self, n: impl Borrow<K>) -> Option<usize> {
+        self.elements.get(n.borrow()).copied()
+    }
+
+    /// Return an iterator of the atoms bonded to atom `n`.
+    pub fn bonded(&self, n: impl Borrow<K>) -> impl Iterator<Item = Neighbor<K>> + '_ {
+        let tol = self.tolerance;
+        let (n, _) = self.elements.get_key_value(n.borrow()).expect("invalid key");
+        self.nh.neighbors_with_cutoffs(n, move |ri: f64, rj: f64| ri + rj + tol)
+    }
+
+    /// Return all bonds as pairs of atoms, each bond reported only once, in
+    /// the same convention as `Neighborhood::all_pairs`.
+    pub fn bonds(&self) -> Vec<(K, Neighbor<K>)> {
+        let tol = self.tolerance;
+        self.nh.all_pairs_with_cutoffs(move |ri: f64, rj: f64| ri + rj + tol).collect()
+    }
+
+    /// Return a reference to the underlying neighborhood.
+    pub fn neighborhood(&self) -> &Neighborhood<K> {
+        &self.nh
+    }
+}
+// c8e04b1f ends here
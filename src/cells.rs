@@ -0,0 +1,268 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::backend::{PeriodicIndex, SpatialIndex};
+use crate::base::*;
+use crate::image::Image;
+use lattice::Lattice;
+use vecfx::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::2f7c9a15][2f7c9a15]]
+/// The average number of points in each bin.
+const POINTS_PER_BIN: f64 = 4.0;
+
+/// Linked-cell (cell list) for periodic systems.
+///
+/// Points are wrapped into the unit cell, and binned in fractional
+/// coordinates, so triclinic cells are handled natively without replicating
+/// the query point over periodic images. Along non-periodic directions,
+/// points are not wrapped, and points outside the cell go to the boundary
+/// bins.
+#[derive(Debug, Clone)]
+pub(crate) struct CellList {
+    lattice: Lattice,
+
+    /// Periodic boundary conditions along three cell directions.
+    pbc: [bool; 3],
+
+    /// The number of bins along three cell directions.
+    nbins: [usize; 3],
+
+    /// The start of points in `bin_points` for each bin (CSR layout).
+    bin_start: Vec<usize>,
+
+    /// Point indices sorted by bin.
+    bin_points: Vec<usize>,
+
+    /// Cartesian positions of points wrapped into the unit cell.
+    wrapped: Vec<Vector3f>,
+
+    /// Lattice translations for wrapping points into the unit cell.
+    shifts: Vec<Image>,
+}
+
+impl CellList {
+    /// Bin `points` in the unit cell of `lattice`.
+    pub fn build(points: &[Point], lattice: Lattice, pbc: [bool; 3]) -> Self {
+        let widths = lattice.widths();
+        let npoints = points.len().max(1) as f64;
+        // the bin length giving required number of points in each bin
+        let l = (lattice.volume().abs() * POINTS_PER_BIN / npoints).cbrt();
+        let nbins = [0, 1, 2].map(|i| ((widths[i] / l).floor() as usize).clamp(1, 1024));
+
+        let mat = lattice.matrix();
+        let mut wrapped = Vec::with_capacity(points.len());
+        let mut shifts = Vec::with_capacity(points.len());
+        let mut bins = Vec::with_capacity(points.len());
+        for &p in points {
+            let f = lattice.to_frac(p);
+            let shift = Image([0, 1, 2].map(|i| if pbc[i] { f[i].floor() as i32 } else { 0 }));
+            let fw = f - shift.to_vector();
+            let b: Vec<_> = (0..3).map(|i| ((fw[i] * nbins[i] as f64) as usize).min(nbins[i] - 1)).collect();
+            bins.push((b[0] * nbins[1] + b[1]) * nbins[2] + b[2]);
+            wrapped.push(Vector3f::from(p) - mat * shift.to_vector());
+            shifts.push(shift);
+        }
+
+        // counting sort of points by bin
+        let ntotal = nbins[0] * nbins[1] * nbins[2];
+        let mut bin_start = vec![0; ntotal + 1];
+        for &b in bins.iter() {
+            bin_start[b + 1] += 1;
+        }
+        for i in 0..ntotal {
+            bin_start[i + 1] += bin_start[i];
+        }
+        let mut bin_points = vec![0; points.len()];
+        let mut cursor = bin_start.clone();
+        for (i, &b) in bins.iter().enumerate() {
+            bin_points[cursor[b]] = i;
+            cursor[b] += 1;
+        }
+
+        Self {
+            lattice,
+            pbc,
+            nbins,
+            bin_start,
+            bin_points,
+            wrapped,
+            shifts,
+        }
+    }
+
+    /// Return bins `[i, j, k]`, which could be out of the unit cell, that
+    /// may have points with distances to `pt` in range `rmin..radius`. Bins
+    /// inside the inner sphere are skipped.
+    fn shell_bins(&self, pt: Point, rmin: f64, radius: f64) -> impl Iterator<Item = [isize; 3]> + '_ {
+        let widths = self.lattice.widths();
+        let fq = self.lattice.to_frac(pt);
+        let pt = Vector3f::from(pt);
+        // a small padding for rounding errors in binning
+        let eps = 1e-8;
+        let ranges: Vec<_> = (0..3)
+            .map(|i| {
+                let n = self.nbins[i] as f64;
+                let rc = radius / widths[i];
+                let lo = ((fq[i] - rc - eps) * n).floor() as isize;
+                let hi = ((fq[i] + rc + eps) * n).floor() as isize;
+                if self.pbc[i] {
+                    lo..=hi
+                } else {
+                    // boundary bins extend to infinity
+                    let nmax = self.nbins[i] as isize - 1;
+                    lo.clamp(0, nmax)..=hi.clamp(0, nmax)
+                }
+            })
+            .collect();
+
+        let (ra, rb, rc) = (ranges[0].clone(), ranges[1].clone(), ranges[2].clone());
+        ra.flat_map(move |i| {
+            let rc = rc.clone();
+            rb.clone().flat_map(move |j| rc.clone().map(move |k| [i, j, k]))
+        })
+        .filter(move |&bin| rmin <= 0.0 || !self.bin_inside(bin, pt, rmin - eps))
+    }
+
+    /// Search points with distances to `pt` in range `rmin..radius`,
+    /// returning point index, distance, and the image relative to the
+    /// original (unwrapped) point. Bins inside the inner sphere are skipped.
+    fn search_images(&self, pt: Point, rmin: f64, radius: f64) -> impl Iterator<Item = (usize, f64, Image)> + '_ {
+        let mat = self.lattice.matrix();
+        let r2 = radius * radius;
+        let rmin2 = rmin * rmin;
+        let [na, nb, nc] = self.nbins.map(|n| n as isize);
+        let bins = self.shell_bins(pt, rmin, radius);
+        let pt = Vector3f::from(pt);
+        bins.flat_map(move |[i, j, k]| {
+            let bin = ((i.rem_euclid(na) * nb + j.rem_euclid(nb)) * nc + k.rem_euclid(nc)) as usize;
+            let tv = Image([i.div_euclid(na) as i32, j.div_euclid(nb) as i32, k.div_euclid(nc) as i32]);
+            let offset = mat * tv.to_vector();
+            self.bin_points[self.bin_start[bin]..self.bin_start[bin + 1]]
+                .iter()
+                .filter_map(move |&p| {
+                    let d2 = (self.wrapped[p] + offset - pt).norm_squared();
+                    if d2 < r2 && d2 >= rmin2 {
+                        Some((p, d2.sqrt(), tv - self.shifts[p]))
+                    } else {
+                        None
+                    }
+                })
+        })
+    }
+}
+
+impl CellList {
+    /// Return true if bin `[i, j, k]`, which could be out of the unit cell,
+    /// is inside the sphere of `radius` around `pt`.
+    fn bin_inside(&self, [i, j, k]: [isize; 3], pt: Vector3f, radius: f64) -> bool {
+        let bin = [i, j, k];
+        // boundary bins extend to infinity along non-periodic directions
+        let unbounded = (0..3).any(|x| !self.pbc[x] && (bin[x] == 0 || bin[x] == self.nbins[x] as isize - 1));
+        if unbounded {
+            return false;
+        }
+        // the bin is a parallelepiped, with the farthest point at a corner
+        let r2 = radius * radius;
+        (0..8).all(|c| {
+            let f: [f64; 3] = [0, 1, 2].map(|x| (bin[x] + (c >> x & 1)) as f64 / self.nbins[x] as f64);
+            (self.lattice.to_cart(f) - pt).norm_squared() < r2
+        })
+    }
+
+    /// Return the distances from `pt` to the original (unwrapped) points.
+    fn distances(&self, pt: Point) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let mat = self.lattice.matrix();
+        let pt = Vector3f::from(pt);
+        self.wrapped
+            .iter()
+            .zip(self.shifts.iter())
+            .enumerate()
+            .map(move |(i, (&p, &s))| (i, (p + mat * s.to_vector() - pt).norm()))
+    }
+}
+
+impl SpatialIndex for CellList {
+    // NOTE: cell list is designed for periodic search, and we simply check all
+    // points for Cartesian search.
+    fn search(&self, pt: Point, radius: f64) -> Vec<(usize, f64)> {
+        self.distances(pt).filter(|&(_, d)| d < radius).collect()
+    }
+
+    fn search_shell(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64)> {
+        self.distances(pt).filter(|&(_, d)| d < rmax && d >= rmin).collect()
+    }
+
+    fn nearest(&self, pt: Point, k: usize) -> Vec<(usize, f64)> {
+        let mut found: Vec<_> = self.distances(pt).collect();
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found.truncate(k);
+        found
+    }
+
+    fn as_periodic(&self) -> Option<&dyn PeriodicIndex> {
+        Some(self)
+    }
+}
+
+impl PeriodicIndex for CellList {
+    fn search_periodic(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64, Image)> {
+        self.search_images(pt, rmin, rmax).collect()
+    }
+}
+// 2f7c9a15 ends here
+
+// [[file:../neighbors.note::e81b6d40][e81b6d40]]
+/// Return all lattice translations `image` for point `p` such that `p +
+/// image` is within `radius` of `pt`, with the distances. Translations are
+/// only made along directions with `pbc` enabled.
+pub(crate) fn images_within(lattice: &Lattice, pbc: [bool; 3], pt: Point, p: Point, radius: f64) -> Vec<(f64, Image)> {
+    let widths = lattice.widths();
+    let df = lattice.to_frac(p) - lattice.to_frac(pt);
+    let ranges: Vec<_> = (0..3)
+        .map(|i| {
+            if !pbc[i] {
+                return 0..=0;
+            }
+            let rc = radius / widths[i];
+            let lo = (-df[i] - rc).ceil() as isize;
+            let hi = (-df[i] + rc).floor() as isize;
+            lo..=hi
+        })
+        .collect();
+
+    let mat = lattice.matrix();
+    let d = Vector3f::from(p) - Vector3f::from(pt);
+    lattice
+        .replicate(ranges[0].clone(), ranges[1].clone(), ranges[2].clone())
+        .filter_map(|tv| {
+            let distance = (d + mat * tv).norm();
+            if distance < radius {
+                Some((distance, Image::from_vector(tv)))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+// e81b6d40 ends here
+
+// [[file:../neighbors.note::*test][test:1]]
+#[test]
+fn test_cell_list_skip_inner_bins() {
+    // points on a regular grid in a cubic cell, with the query point at the center
+    let points: Vec<Point> = (0..8000).map(|i| [i % 20, (i / 20) % 20, i / 400].map(|x| x as f64)).collect();
+    let lattice = Lattice::new([[20.0, 0.0, 0.0], [0.0, 20.0, 0.0], [0.0, 0.0, 20.0]]);
+    let cells = CellList::build(&points, lattice, [true; 3]);
+    let pt = [9.6, 9.6, 9.6];
+
+    let all: Vec<_> = cells.shell_bins(pt, 0.0, 8.0).collect();
+    let shell: Vec<_> = cells.shell_bins(pt, 7.0, 8.0).collect();
+    assert!(shell.len() < all.len());
+    // the bin holding the query point is inside the inner sphere
+    let [i, j, k] = [0, 1, 2].map(|x| (pt[x] / 20.0 * cells.nbins[x] as f64).floor() as isize);
+    assert!(all.contains(&[i, j, k]));
+    assert!(!shell.contains(&[i, j, k]));
+    // the same as checking all points away from cell boundaries
+    assert_eq!(cells.search_images(pt, 7.0, 8.0).count(), cells.search_shell(pt, 7.0, 8.0).len());
+}
+// test:1 ends here
@@ -0,0 +1,58 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::4c9d2e81][4c9d2e81]]
+impl<K: Key> Neighborhood<K> {
+    /// Return all pairs of nodes closer than `min_distance`, for validating
+    /// generated structures. Periodic images are taken into account, and
+    /// each pair is reported only once with the same convention as
+    /// `all_pairs`. Pairs are sorted by distance, the worst clash first.
+    /// Nodes are processed in parallel if the `rayon` feature is enabled.
+    pub fn clashes(&self, min_distance: f64) -> Vec<(K, Neighbor<K>)> {
+        let search = |i: usize| -> Vec<_> { self.half_neighbors(i, min_distance).map(|m| (i, m)).collect() };
+
+        #[cfg(feature = "rayon")]
+        let mut found: Vec<_> = (0..self.points.len()).into_par_iter().flat_map_iter(search).collect();
+        #[cfg(not(feature = "rayon"))]
+        let mut found: Vec<_> = (0..self.points.len()).flat_map(search).collect();
+
+        // sort by distance, and then by nodes and image for reproducible
+        // results
+        found.sort_by_cached_key(|(i, m)| {
+            let j = self.points.get_index_of(&m.node).expect("invalid node");
+            (m.distance.to_bits(), *i, j, m.image)
+        });
+        found
+            .into_iter()
+            .map(|(i, m)| (self.points.get_index(i).expect("invalid index").0.clone(), m))
+            .collect()
+    }
+
+    /// Return any pair of nodes closer than `min_distance`, or None if there
+    /// is no clash. The search stops at the first clash found, which is
+    /// much faster than `clashes` for checking invalid structures.
+    pub fn find_clash(&self, min_distance: f64) -> Option<(K, Neighbor<K>)> {
+        let search = |i: usize| {
+            let m = self.half_neighbors(i, min_distance).next()?;
+            let (n, _) = self.points.get_index(i).expect("invalid index");
+            Some((n.clone(), m))
+        };
+
+        #[cfg(feature = "rayon")]
+        let found = (0..self.points.len()).into_par_iter().find_map_any(search);
+        #[cfg(not(feature = "rayon"))]
+        let found = (0..self.points.len()).find_map(search);
+
+        found
+    }
+
+    /// Return true if there is no pair of nodes closer than `min_distance`.
+    pub fn is_clash_free(&self, min_distance: f64) -> bool {
+        self.find_clash(min_distance).is_none()
+    }
+}
+// 4c9d2e81 ends here
@@ -0,0 +1,95 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use std::borrow::Borrow;
+// import:1 ends here
+
+// [[file:../neighbors.note::f1c3a860][f1c3a860]]
+/// Rule for combining cutoff radii of two nodes into the cutoff distance of
+/// the pair. The combined cutoff should not decrease with either radius,
+/// which is required for pruning spatial search.
+pub trait CutoffRule {
+    /// Return the cutoff distance for a pair of nodes with cutoff radii `ri`
+    /// and `rj`.
+    fn cutoff(&self, ri: f64, rj: f64) -> f64;
+}
+
+/// Common rules for combining cutoff radii.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+    /// `ri + rj`, as in ASE's `NeighborList`.
+    Sum,
+
+    /// The larger one of `ri` and `rj`.
+    Max,
+}
+
+impl CutoffRule for Combine {
+    fn cutoff(&self, ri: f64, rj: f64) -> f64 {
+        match self {
+            Combine::Sum => ri + rj,
+            Combine::Max => ri.max(rj),
+        }
+    }
+}
+
+/// Custom rule from closure, e.g. `|ri, rj| ri + rj + 0.4`.
+impl<F> CutoffRule for F
+where
+    F: Fn(f64, f64) -> f64,
+{
+    fn cutoff(&self, ri: f64, rj: f64) -> f64 {
+        self(ri, rj)
+    }
+}
+// f1c3a860 ends here
+
+// [[file:../neighbors.note::07be59d2][07be59d2]]
+impl<K: Key> Neighborhood<K> {
+    /// Set cutoff radius for each node. Nodes without cutoff radius are
+    /// treated as zero.
+    pub fn set_cutoffs<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, f64)>,
+    {
+        for (k, r) in iter {
+            assert!(r >= 0.0, "invalid cutoff radius for node {k:?}: {r}");
+            self.cutoffs.insert(k, r);
+        }
+        self.max_cutoff = self.cutoffs.values().copied().fold(0.0, f64::max);
+    }
+
+    /// Return the cutoff radius of node `n`.
+    pub fn get_cutoff(&self, n: impl Borrow<K>) -> f64 {
+        self.cutoffs.get(n.borrow()).copied().unwrap_or(0.0)
+    }
+
+    /// Return an iterator of the nodes connected to the node `n`, using
+    /// per-node cutoff radii combined by `rule`.
+    ///
+    /// A neighbor `m` is reported if its distance to `n` is less than
+    /// `rule.cutoff(r_n, r_m)`.
+    pub fn neighbors_with_cutoffs<'a, R>(&'a self, n: impl Borrow<K>, rule: R) -> impl Iterator<Item = Neighbor<K>> + 'a
+    where
+        R: CutoffRule + 'a,
+    {
+        let (n, _) = self.points.get_key_value(n.borrow()).expect("invalid key");
+        let rn = self.get_cutoff(n);
+        // prune spatial search with the maximum cutoff
+        let radius = rule.cutoff(rn, self.max_cutoff);
+        self.neighbors(n, radius)
+            .filter(move |m| m.distance < rule.cutoff(rn, self.get_cutoff(&m.node)))
+    }
+
+    /// Return an iterator over all pairs of nodes, using per-node cutoff
+    /// radii combined by `rule`. Each pair is visited only once, with the
+    /// same convention as `all_pairs`.
+    pub fn all_pairs_with_cutoffs<'a, R>(&'a self, rule: R) -> impl Iterator<Item = (K, Neighbor<K>)> + 'a
+    where
+        R: CutoffRule + 'a,
+    {
+        let radius = rule.cutoff(self.max_cutoff, self.max_cutoff);
+        self.all_pairs(radius)
+            .filter(move |(n, m)| m.distance < rule.cutoff(self.get_cutoff(n), self.get_cutoff(&m.node)))
+    }
+}
+// 07be59d2 ends here
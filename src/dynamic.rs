@@ -0,0 +1,421 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::backend::{bounding_box, Backend, SpatialIndex};
+use crate::base::*;
+use crate::cells::images_within;
+use crate::image::Image;
+use indexmap::{IndexMap, IndexSet};
+use lattice::Lattice;
+use std::sync::Arc;
+use vecfx::{Matrix3f, Vector3f};
+// import:1 ends here
+
+// [[file:../neighbors.note::5e9b0c72][5e9b0c72]]
+/// Rebuild the spatial index when the number of points changed since last build
+/// exceeds this fraction of the points in the tree.
+const REBUILD_RATIO: f64 = 0.2;
+
+/// Never rebuild the spatial index for only a few changed points.
+const REBUILD_MIN: usize = 16;
+
+/// Return the max number of points out of the tree, which are scanned
+/// linearly in every query (and for every image in periodic search). This
+/// grows as the square root of the number of points in the tree, so that
+/// the linear scan stays cheap compared with the tree search.
+fn max_extra(ntree: usize) -> usize {
+    REBUILD_MIN.max((ntree as f64).sqrt() as usize)
+}
+
+/// Rebuild the spatial index when the cell is distorted since last build by
+/// more than this ratio of the largest to the smallest stretch, which
+/// enlarges the search volume in the tree.
+const MAX_DISTORTION: f64 = 1.1;
+
+/// Affine deformation `x -> mat * x + shift` from points in the tree to
+/// their current positions, as the cell deforms.
+#[derive(Debug, Clone, Copy)]
+struct Strain {
+    mat: Matrix3f,
+    inv: Matrix3f,
+    shift: Vector3f,
+    /// The smallest and largest stretch ratios (singular values of `mat`).
+    stretch: [f64; 2],
+}
+
+impl Strain {
+    fn new(mat: Matrix3f, shift: Vector3f) -> Self {
+        let inv = mat.try_inverse().expect("singular deformation");
+        let sv = mat.singular_values();
+        Self {
+            mat,
+            inv,
+            shift,
+            stretch: [sv.min(), sv.max()],
+        }
+    }
+
+    /// Apply deformation `x -> mat * x + shift` after this one.
+    fn then(&self, mat: Matrix3f, shift: Vector3f) -> Self {
+        Self::new(mat * self.mat, mat * self.shift + shift)
+    }
+
+    /// Map point `pt` in current positions back to the tree.
+    fn inverse_point(self, pt: Point) -> Point {
+        (self.inv * (Vector3f::from(pt) - self.shift)).into()
+    }
+
+    /// Return the radius in the tree enclosing all points within `radius`
+    /// in current positions.
+    fn inverse_radius(self, radius: f64) -> f64 {
+        radius / self.stretch[0] * (1.0 + 1e-8) + 1e-8
+    }
+
+    /// Return the radius in the tree enclosed by all points within
+    /// `radius` in current positions.
+    fn inverse_inner_radius(self, radius: f64) -> f64 {
+        (radius / self.stretch[1] * (1.0 - 1e-8) - 1e-8).max(0.0)
+    }
+
+    /// Return the ratio of the largest to the smallest stretch.
+    fn distortion(&self) -> f64 {
+        self.stretch[1] / self.stretch[0]
+    }
+}
+
+/// A spatial index supporting cheap point moving, insertion and removal.
+///
+/// The backend spatial index (octree, cell list, ...) is a snapshot of
+/// points at the time it was built. Points changed since then are marked
+/// stale in the tree and searched linearly, until there are too many of them
+/// and the tree has to be rebuilt.
+#[derive(Debug, Clone)]
+pub(crate) struct DynamicIndex {
+    /// Spatial index snapshot. None for empty point set.
+    tree: Option<Arc<dyn SpatialIndex>>,
+
+    /// The index of point in `Neighborhood.points` for each point in the
+    /// tree, or None if it is stale.
+    slots: Vec<Option<usize>>,
+
+    /// The slot in tree for each point in `Neighborhood.points`, or None if
+    /// the point is not in the tree.
+    lookup: Vec<Option<usize>>,
+
+    /// Points not in the tree, which will be searched linearly.
+    extra: IndexSet<usize>,
+
+    /// The number of stale slots in the tree.
+    nstale: usize,
+
+    /// The lower and upper corners of a box enclosing all points. The box
+    /// only grows until next build.
+    bounds: [Point; 2],
+
+    /// The deformation of points since last build, or None if the tree is
+    /// in current positions.
+    strain: Option<Strain>,
+
+    /// The positions of points in the tree at the time of build, which are
+    /// wrapped into the home cell if required.
+    positions: Vec<Point>,
+
+    /// The image of original point located at each position in the tree.
+    /// Empty if points are not wrapped.
+    images: Vec<Image>,
+}
+
+impl DynamicIndex {
+    /// Build the index from scratch using spatial index algorithm `backend`.
+    /// Points are wrapped into the home cell along periodic directions if
+    /// `wrap` is true and `lattice` is available.
+    pub fn build<K>(
+        points: &IndexMap<K, Point>,
+        lattice: Option<Lattice>,
+        pbc: [bool; 3],
+        backend: Backend,
+        wrap: bool,
+    ) -> Self {
+        let n = points.len();
+        let mut positions: Vec<_> = points.values().copied().collect();
+        let mut images = vec![];
+        if let Some(lattice) = lattice.filter(|_| wrap) {
+            images = positions.iter_mut().map(|p| wrap_point(&lattice, pbc, p)).collect();
+        }
+        let bounds = bounding_box(&positions);
+        let tree = if n > 0 {
+            Some(backend.build(&positions, lattice, pbc))
+        } else {
+            None
+        };
+
+        Self {
+            tree,
+            slots: (0..n).map(Some).collect(),
+            lookup: (0..n).map(Some).collect(),
+            extra: IndexSet::new(),
+            nstale: 0,
+            bounds,
+            strain: None,
+            positions,
+            images,
+        }
+    }
+
+    /// Mark the point with index `i` as moved to `p`.
+    pub fn moved(&mut self, i: usize, p: Point) {
+        if let Some(slot) = self.lookup[i].take() {
+            self.slots[slot] = None;
+            self.nstale += 1;
+        }
+        self.extra.insert(i);
+        self.enclose(p);
+    }
+
+    /// Record a new point `p` appended with index `i`.
+    pub fn inserted(&mut self, i: usize, p: Point) {
+        debug_assert_eq!(i, self.lookup.len());
+        self.lookup.push(None);
+        self.extra.insert(i);
+        self.enclose(p);
+    }
+
+    fn enclose(&mut self, p: Point) {
+        let [lo, hi] = &mut self.bounds;
+        for k in 0..3 {
+            lo[k] = lo[k].min(p[k]);
+            hi[k] = hi[k].max(p[k]);
+        }
+    }
+
+    /// Record the deformation of all points by affine map `x -> mat * x +
+    /// shift`. The tree is kept as it is, and queries are mapped back to it.
+    pub fn deformed(&mut self, mat: Matrix3f, shift: Vector3f) {
+        self.strain = Some(match self.strain {
+            Some(strain) => strain.then(mat, shift),
+            None => Strain::new(mat, shift),
+        });
+
+        // the deformed box enclosing all corners
+        if let Some([lo, hi]) = self.bounds() {
+            let mut bounds = [[f64::INFINITY; 3], [f64::NEG_INFINITY; 3]];
+            std::mem::swap(&mut self.bounds, &mut bounds);
+            for i in 0..8 {
+                let corner: Point = [0, 1, 2].map(|k| if i >> k & 1 == 0 { lo[k] } else { hi[k] });
+                self.enclose((mat * Vector3f::from(corner) + shift).into());
+            }
+        }
+    }
+
+    /// Return the lower and upper corners of a box enclosing all points.
+    /// Return None if there is no point.
+    pub fn bounds(&self) -> Option<[Point; 2]> {
+        let [lo, hi] = self.bounds;
+        (0..3).all(|k| lo[k] <= hi[k]).then_some(self.bounds)
+    }
+
+    /// Record the removal of point with index `i`, using the same semantics
+    /// as `IndexMap::swap_remove`: the last point is moved to index `i`.
+    pub fn swap_removed(&mut self, i: usize) {
+        if let Some(slot) = self.lookup[i] {
+            self.slots[slot] = None;
+            self.nstale += 1;
+        }
+        self.extra.swap_remove(&i);
+
+        let last = self.lookup.len() - 1;
+        self.lookup.swap_remove(i);
+        if i != last {
+            if let Some(slot) = self.lookup[i] {
+                self.slots[slot] = Some(i);
+            }
+            if self.extra.swap_remove(&last) {
+                self.extra.insert(i);
+            }
+        }
+    }
+
+    /// Return true if too many points changed or the cell is distorted too
+    /// much since last build.
+    pub fn is_unbalanced(&self) -> bool {
+        let nchanged = self.nstale + self.extra.len();
+        nchanged > REBUILD_MIN && nchanged as f64 > REBUILD_RATIO * self.slots.len() as f64
+            || self.extra.len() > max_extra(self.slots.len())
+            || self.strain.is_some_and(|strain| strain.distortion() > MAX_DISTORTION)
+    }
+
+    /// Return the image of original point located at position of `slot`
+    /// in the tree.
+    fn image(&self, slot: usize) -> Image {
+        self.images.get(slot).copied().unwrap_or_default()
+    }
+
+    /// Return the current position of `slot` in the tree.
+    fn position(&self, slot: usize) -> Vector3f {
+        let p = Vector3f::from(self.positions[slot]);
+        match self.strain {
+            Some(strain) => strain.mat * p + strain.shift,
+            None => p,
+        }
+    }
+
+    /// Return slots of valid points in the tree with distances to `pt` in
+    /// range `rmin..rmax`, together with their distances in current
+    /// positions.
+    fn search_tree(&self, tree: &dyn SpatialIndex, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64)> {
+        match self.strain {
+            None => tree
+                .search_shell(pt, rmin, rmax)
+                .into_iter()
+                .filter(|&(slot, _)| self.slots[slot].is_some())
+                .collect(),
+            Some(strain) => tree
+                .search_shell(
+                    strain.inverse_point(pt),
+                    strain.inverse_inner_radius(rmin),
+                    strain.inverse_radius(rmax),
+                )
+                .into_iter()
+                .filter(|&(slot, _)| self.slots[slot].is_some())
+                .map(|(slot, _)| (slot, (self.position(slot) - Vector3f::from(pt)).norm()))
+                .filter(|&(_, d)| d < rmax && d >= rmin)
+                .collect(),
+        }
+    }
+
+    /// Return indices of `points` with distances to `pt` in range
+    /// `rmin..rmax`, together with their distances and images of original
+    /// points found, which are not the origin only for points wrapped into
+    /// the home cell.
+    pub fn search_shell<'a, K>(
+        &'a self,
+        points: &'a IndexMap<K, Point>,
+        pt: Point,
+        rmin: f64,
+        rmax: f64,
+    ) -> impl Iterator<Item = (usize, f64, Image)> + 'a {
+        let in_tree = self.tree.iter().flat_map(move |tree| {
+            self.search_tree(tree.as_ref(), pt, rmin, rmax)
+                .into_iter()
+                .filter_map(move |(slot, d)| self.slots[slot].map(|i| (i, d, self.image(slot))))
+        });
+
+        let in_extra = self.extra.iter().filter_map(move |&i| {
+            let d = distance(points, i, pt);
+            if d < rmax && d >= rmin {
+                Some((i, d, Image::ORIGIN))
+            } else {
+                None
+            }
+        });
+
+        in_tree.chain(in_extra)
+    }
+
+    /// Return indices of at most `k` nearest `points` to `pt`, together with
+    /// their distances, sorted by distance.
+    pub fn nearest<K>(&self, points: &IndexMap<K, Point>, pt: Point, k: usize) -> Vec<(usize, f64)> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut found = vec![];
+        if let Some(tree) = self.tree.as_ref() {
+            let q = self.strain.map_or(pt, |strain| strain.inverse_point(pt));
+            // stale points in tree will be filtered out
+            found = tree
+                .nearest(q, k + self.nstale)
+                .into_iter()
+                .filter(|&(slot, _)| self.slots[slot].is_some())
+                .collect();
+            // distances are distorted by the deformation, so the k nearest
+            // points in current positions could be a bit farther in tree
+            if let Some(strain) = self.strain {
+                found = match found.get(k - 1) {
+                    // all k points are within this radius in current positions,
+                    // which is mapped back to the tree in `search_tree`
+                    Some(&(_, d)) => {
+                        let r = d * strain.stretch[1] * (1.0 + 1e-8) + 1e-8;
+                        self.search_tree(tree.as_ref(), pt, 0.0, r)
+                    }
+                    None => found
+                        .into_iter()
+                        .map(|(slot, _)| (slot, (self.position(slot) - Vector3f::from(pt)).norm()))
+                        .collect(),
+                };
+            }
+            found = found.into_iter().filter_map(|(slot, d)| self.slots[slot].map(|i| (i, d))).collect();
+        }
+        found.extend(self.extra.iter().map(|&i| (i, distance(points, i, pt))));
+        found.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        found.truncate(k);
+        found
+    }
+
+    /// Return true if periodic images can be searched natively.
+    pub fn is_periodic(&self) -> bool {
+        self.tree.as_ref().is_some_and(|tree| tree.as_periodic().is_some())
+    }
+
+    /// Search periodic images of `points` with distances to `pt` in range
+    /// `rmin..rmax` natively. Return point indices, distances and images
+    /// relative to the original points. Images are only generated along
+    /// directions with `pbc` enabled, using the current `lattice`. Return
+    /// None if periodic search is not supported natively.
+    pub fn search_periodic<'a, K>(
+        &'a self,
+        points: &'a IndexMap<K, Point>,
+        pt: Point,
+        rmin: f64,
+        rmax: f64,
+        lattice: Lattice,
+        pbc: [bool; 3],
+    ) -> Option<impl Iterator<Item = (usize, f64, Image)> + 'a> {
+        let tree = self.tree.as_ref()?.as_periodic()?;
+        let in_tree: Vec<_> = match self.strain {
+            None => tree
+                .search_periodic(pt, rmin, rmax)
+                .into_iter()
+                .filter_map(|(slot, d, image)| self.slots[slot].map(|i| (i, d, image + self.image(slot))))
+                .collect(),
+            // images are kept by the deformation of the cell
+            Some(strain) => {
+                let mat = lattice.matrix();
+                let q = strain.inverse_point(pt);
+                tree.search_periodic(q, strain.inverse_inner_radius(rmin), strain.inverse_radius(rmax))
+                    .into_iter()
+                    .filter_map(|(slot, _, image)| {
+                        let i = self.slots[slot]?;
+                        let d = (self.position(slot) + mat * image.to_vector() - Vector3f::from(pt)).norm();
+                        (d < rmax && d >= rmin).then_some((i, d, image + self.image(slot)))
+                    })
+                    .collect()
+            }
+        };
+
+        let in_extra = self.extra.iter().flat_map(move |&i| {
+            let (_, &p) = points.get_index(i).expect("invalid index");
+            images_within(&lattice, pbc, pt, p, rmax)
+                .into_iter()
+                .filter(move |&(d, _)| d >= rmin)
+                .map(move |(d, image)| (i, d, image))
+        });
+
+        Some(in_tree.into_iter().chain(in_extra))
+    }
+}
+
+/// Wrap point `p` into the home cell of `lattice` along directions with
+/// `pbc` enabled. Return the image of original point at the wrapped
+/// position.
+fn wrap_point(lattice: &Lattice, pbc: [bool; 3], p: &mut Point) -> Image {
+    let f = lattice.to_frac(*p);
+    let n = Vector3f::from([0, 1, 2].map(|k| if pbc[k] { f[k].floor() } else { 0.0 }));
+    *p = lattice.to_cart(f - n).into();
+    Image::from_vector(-n)
+}
+
+/// Return the distance from point with index `i` in `points` to `pt`.
+fn distance<K>(points: &IndexMap<K, Point>, i: usize, pt: Point) -> f64 {
+    let (_, p) = points.get_index(i).expect("invalid index");
+    (0..3).map(|k| (p[k] - pt[k]).powi(2)).sum::<f64>().sqrt()
+}
+// 5e9b0c72 ends here
@@ -0,0 +1,127 @@
+// [[file:../neighbors.note::4a9d2e61][4a9d2e61]]
+/// Element symbols indexed by atomic number.
+const SYMBOLS: [&str; 119] = [
+    "X", "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl", "Ar", "K",
+    "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As", "Se", "Br", "Kr", "Rb",
+    "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In", "Sn", "Sb", "Te", "I", "Xe", "Cs",
+    "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta",
+    "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl", "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa",
+    "U", "Np", "Pu", "Am", "Cm", "Bk", "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt",
+    "Ds", "Rg", "Cn", "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Covalent radii in Angstrom indexed by atomic number, for elements up to
+/// Cm.
+///
+/// Reference
+/// ---------
+/// - Cordero, B. et al. Covalent radii revisited. Dalton Trans. 2008, 2832–2838.
+#[rustfmt::skip]
+const COVALENT_RADII: [f64; 97] = [
+    0.20,
+    0.31, 0.28,
+    1.28, 0.96, 0.84, 0.76, 0.71, 0.66, 0.57, 0.58,
+    1.66, 1.41, 1.21, 1.11, 1.07, 1.05, 1.02, 1.06,
+    2.03, 1.76, 1.70, 1.60, 1.53, 1.39, 1.39, 1.32, 1.26, 1.24, 1.32, 1.22, 1.22, 1.20, 1.19, 1.20, 1.20, 1.16,
+    2.20, 1.95, 1.90, 1.75, 1.64, 1.54, 1.47, 1.46, 1.42, 1.39, 1.45, 1.44, 1.42, 1.39, 1.39, 1.38, 1.39, 1.40,
+    2.44, 2.15,
+    2.07, 2.04, 2.03, 2.01, 1.99, 1.98, 1.98, 1.96, 1.94, 1.92, 1.92, 1.89, 1.90, 1.87, 1.87,
+    1.75, 1.70, 1.62, 1.51, 1.44, 1.41, 1.36, 1.36, 1.32, 1.45, 1.46, 1.48, 1.40, 1.50, 1.50,
+    2.60, 2.21,
+    2.15, 2.06, 2.00, 1.96, 1.90, 1.87, 1.80, 1.69,
+];
+
+/// van der Waals radii in Angstrom indexed by atomic number. Zero for
+/// elements without data.
+///
+/// Reference
+/// ---------
+/// - Bondi, A. van der Waals Volumes and Radii. J. Phys. Chem. 1964, 68, 441–451.
+/// - Mantina, M. et al. Consistent van der Waals Radii for the Whole Main
+///   Group. J. Phys. Chem. A 2009, 113, 5806–5812.
+#[rustfmt::skip]
+const VDW_RADII: [f64; 93] = [
+    0.00,
+    1.20, 1.40,
+    1.82, 1.53, 1.92, 1.70, 1.55, 1.52, 1.47, 1.54,
+    2.27, 1.73, 1.84, 2.10, 1.80, 1.80, 1.75, 1.88,
+    2.75, 2.31, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 1.63, 1.40, 1.39, 1.87, 2.11, 1.85, 1.90, 1.85, 2.02,
+    3.03, 2.49, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 1.63, 1.72, 1.58, 1.93, 2.17, 2.06, 2.06, 1.98, 2.16,
+    3.43, 2.68,
+    0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 0.00,
+    0.00, 0.00, 0.00, 0.00, 0.00, 0.00, 1.72, 1.66, 1.55, 1.96, 2.02, 2.07, 1.97, 2.02, 2.20,
+    3.48, 2.83,
+    0.00, 0.00, 0.00, 1.86,
+];
+
+/// Return the atomic number of element `symbol` (case insensitive).
+pub fn atomic_number(symbol: &str) -> Option<usize> {
+    SYMBOLS
+        .iter()
+        .skip(1)
+        .position(|s| s.eq_ignore_ascii_case(symbol))
+        .map(|i| i + 1)
+}
+
+/// Return the symbol of element with atomic number `z`.
+pub fn element_symbol(z: usize) -> Option<&'static str> {
+    SYMBOLS.get(z).filter(|_| z > 0).copied()
+}
+
+/// Return the covalent radius of element with atomic number `z`.
+pub fn covalent_radius(z: usize) -> Option<f64> {
+    COVALENT_RADII.get(z).filter(|_| z > 0).copied()
+}
+
+/// Return the van der Waals radius of element with atomic number `z`.
+pub fn vdw_radius(z: usize) -> Option<f64> {
+    VDW_RADII.get(z).copied().filter(|&r| r > 0.0)
+}
+// 4a9d2e61 ends here
+
+// [[file:../neighbors.note::b05f3c9e][b05f3c9e]]
+/// Types that can be interpreted as a chemical element.
+pub trait AsElement {
+    /// Return the atomic number, or None if invalid.
+    fn atomic_number(&self) -> Option<usize>;
+}
+
+impl AsElement for usize {
+    fn atomic_number(&self) -> Option<usize> {
+        element_symbol(*self).map(|_| *self)
+    }
+}
+
+impl AsElement for &str {
+    fn atomic_number(&self) -> Option<usize> {
+        atomic_number(self)
+    }
+}
+
+impl AsElement for String {
+    fn atomic_number(&self) -> Option<usize> {
+        atomic_number(self)
+    }
+}
+// b05f3c9e ends here
+
+// [[file:../neighbors.note::*test][test:1]]
+#[test]
+fn test_elements() {
+    assert_eq!(atomic_number("C"), Some(6));
+    assert_eq!(atomic_number("fe"), Some(26));
+    assert_eq!(atomic_number("X"), None);
+    assert_eq!(atomic_number("Xx"), None);
+    assert_eq!(element_symbol(118), Some("Og"));
+    assert_eq!(element_symbol(0), None);
+    assert_eq!(covalent_radius(1), Some(0.31));
+    assert_eq!(covalent_radius(96), Some(1.69));
+    assert_eq!(covalent_radius(97), None);
+    assert_eq!(vdw_radius(6), Some(1.70));
+    assert_eq!(vdw_radius(26), None);
+    assert_eq!(vdw_radius(92), Some(1.86));
+    for z in [8, 14, 29, 56, 79, 92] {
+        assert_eq!(element_symbol(z).and_then(atomic_number), Some(z));
+    }
+}
+// test:1 ends here
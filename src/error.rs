@@ -0,0 +1,84 @@
+// [[file:../neighbors.note::3b0e6f21][3b0e6f21]]
+use crate::base::Point;
+
+/// Errors for neighborhood search.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// There is no point associated with the key (in debug format).
+    InvalidKey(String),
+
+    /// There are no points in the neighborhood, or `update` was never called.
+    EmptyNeighborhood,
+
+    /// The coordinates contain NaN or infinite values.
+    NonFiniteCoordinates(Point),
+
+    /// The search radius is negative or not finite.
+    InvalidRadius(f64),
+
+    /// The lattice vectors are linearly dependent (zero cell volume).
+    DegenerateLattice,
+
+    /// Unknown chemical element.
+    InvalidElement(String),
+
+    /// There is no lattice, which is required for fractional coordinates.
+    NoLattice,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidKey(n) => write!(f, "invalid key: {n}"),
+            Error::EmptyNeighborhood => write!(f, "no points in neighborhood"),
+            Error::NonFiniteCoordinates(p) => write!(f, "non-finite coordinates: {p:?}"),
+            Error::InvalidRadius(r) => write!(f, "invalid search radius: {r}"),
+            Error::DegenerateLattice => write!(f, "degenerate lattice"),
+            Error::InvalidElement(e) => write!(f, "invalid element: {e}"),
+            Error::NoLattice => write!(f, "no lattice"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A specialized `Result` type for neighborhood search.
+pub type Result<T> = std::result::Result<T, Error>;
+// 3b0e6f21 ends here
+
+// [[file:../neighbors.note::c5a1d7e4][c5a1d7e4]]
+pub(crate) fn check_point(p: Point) -> Result<()> {
+    if p.iter().all(|x| x.is_finite()) {
+        Ok(())
+    } else {
+        Err(Error::NonFiniteCoordinates(p))
+    }
+}
+
+pub(crate) fn check_radius(r: f64) -> Result<()> {
+    if r.is_finite() && r >= 0.0 {
+        Ok(())
+    } else {
+        Err(Error::InvalidRadius(r))
+    }
+}
+
+/// Check lattice vectors before calling `Lattice::new`, which will panic for
+/// a singular matrix.
+pub(crate) fn check_lattice_vectors(mat: [[f64; 3]; 3]) -> Result<()> {
+    if !mat.iter().flatten().all(|x| x.is_finite()) {
+        return Err(Error::DegenerateLattice);
+    }
+    let [a, b, c] = mat;
+    let norm = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let bxc = [b[1] * c[2] - b[2] * c[1], b[2] * c[0] - b[0] * c[2], b[0] * c[1] - b[1] * c[0]];
+    let volume = a[0] * bxc[0] + a[1] * bxc[1] + a[2] * bxc[2];
+    // compare with the volume of a cuboid having the same edge lengths
+    let scale = norm(a) * norm(b) * norm(c);
+    if scale > 0.0 && volume.abs() > 1e-8 * scale {
+        Ok(())
+    } else {
+        Err(Error::DegenerateLattice)
+    }
+}
+// c5a1d7e4 ends here
@@ -0,0 +1,95 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::b3f07c5e][b3f07c5e]]
+/// Rule for excluding the host node from its own neighbors in `neighbors`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SelfExclusion {
+    /// Exclude the host node itself in the origin cell, regardless of
+    /// distance. Other nodes coincident with the host are kept.
+    #[default]
+    Identity,
+
+    /// Exclude all nodes closer to the host than this distance, including
+    /// the host itself and nodes coincident with it, such as disordered
+    /// sites with partial occupancies.
+    Tolerance(f64),
+
+    /// Keep the host node itself, at zero distance.
+    Include,
+}
+
+impl<K: Key> Neighborhood<K> {
+    /// Set the rule for excluding the host node from its neighbors. The
+    /// default is `SelfExclusion::Identity`. Periodic images of the host
+    /// node are controlled separately by `set_self_images`.
+    pub fn set_self_exclusion(&mut self, rule: SelfExclusion) {
+        if let SelfExclusion::Tolerance(tol) = rule {
+            assert!(tol >= 0.0, "invalid tolerance: {tol}");
+        }
+        self.self_exclusion = rule;
+    }
+
+    /// Return true if neighbor `m` of host node `n` is excluded by the
+    /// self exclusion rule.
+    pub(crate) fn is_self(&self, n: &K, m: &Neighbor<K>) -> bool {
+        match self.self_exclusion {
+            SelfExclusion::Identity => &m.node == n && m.image.is_none_or(|image| image.is_origin()),
+            SelfExclusion::Tolerance(tol) => m.distance < tol,
+            SelfExclusion::Include => false,
+        }
+    }
+
+    /// Return true if neighbor `m` of host node `n` is excluded by the self
+    /// exclusion rule, or as a periodic image of the host.
+    pub(crate) fn is_excluded(&self, n: &K, m: &Neighbor<K>) -> bool {
+        let self_image = &m.node == n && m.image.is_some_and(|image| !image.is_origin());
+        self.is_self(n, m) || self_image && !self.self_images
+    }
+}
+// b3f07c5e ends here
+
+// [[file:../neighbors.note::7e2a94d1][7e2a94d1]]
+impl<K: Key> Neighborhood<K> {
+    /// Return groups of nodes overlapping each other, closer than distance
+    /// `threshold` directly or through other nodes in the same group, for
+    /// finding duplicated atoms or disordered sites. Periodic images are
+    /// taken into account. Only groups with more than one node are
+    /// returned, in insertion order of their first nodes.
+    pub fn overlapping_sites(&self, threshold: f64) -> Vec<Vec<K>> {
+        // union-find over point indices
+        let mut parent: Vec<_> = (0..self.points.len()).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        for (n, m) in self.all_pairs(threshold) {
+            let i = self.points.get_index_of(&n).expect("invalid node");
+            let j = self.points.get_index_of(&m.node).expect("invalid node");
+            let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+            // the root is the first node in the group
+            parent[ri.max(rj)] = ri.min(rj);
+        }
+
+        let mut groups: Vec<Vec<K>> = vec![];
+        let mut slots: Vec<Option<usize>> = vec![None; self.points.len()];
+        for (i, n) in self.points.keys().enumerate() {
+            let root = find(&mut parent, i);
+            match slots[root] {
+                Some(g) => groups[g].push(n.clone()),
+                None => {
+                    slots[root] = Some(groups.len());
+                    groups.push(vec![n.clone()]);
+                }
+            }
+        }
+        groups.retain(|g| g.len() > 1);
+        groups
+    }
+}
+// 7e2a94d1 ends here
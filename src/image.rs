@@ -0,0 +1,126 @@
+// [[file:../neighbors.note::*import][import:1]]
+use std::ops::{Add, AddAssign, Index, Neg, Sub, SubAssign};
+use vecfx::Vector3f;
+// import:1 ends here
+
+// [[file:../neighbors.note::9e4b7c2d][9e4b7c2d]]
+/// Periodic image of a point, as integer lattice translation relative to
+/// the origin cell.
+///
+/// Images can be compared, hashed and ordered, so they can be used as keys
+/// in periodic graphs together with node pairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Image(pub [i32; 3]);
+
+impl Image {
+    /// The origin cell.
+    pub const ORIGIN: Image = Image([0; 3]);
+
+    /// Constructs from translations along three lattice vectors.
+    pub fn new(a: i32, b: i32, c: i32) -> Self {
+        Self([a, b, c])
+    }
+
+    /// Return true if this is the origin cell.
+    pub fn is_origin(&self) -> bool {
+        self.0 == [0; 3]
+    }
+
+    /// Return the translations as floats, for multiplying with lattice
+    /// matrix.
+    pub fn to_vector(&self) -> Vector3f {
+        Vector3f::from(self.0.map(|x| x as f64))
+    }
+
+    /// Constructs from fractional translation vector in floats by rounding.
+    pub(crate) fn from_vector(v: Vector3f) -> Self {
+        Self([0, 1, 2].map(|i| v[i].round() as i32))
+    }
+}
+
+impl From<[i32; 3]> for Image {
+    fn from(v: [i32; 3]) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Image> for [i32; 3] {
+    fn from(image: Image) -> Self {
+        image.0
+    }
+}
+
+impl From<Image> for Vector3f {
+    fn from(image: Image) -> Self {
+        image.to_vector()
+    }
+}
+
+impl Index<usize> for Image {
+    type Output = i32;
+
+    fn index(&self, i: usize) -> &i32 {
+        &self.0[i]
+    }
+}
+
+impl Add for Image {
+    type Output = Image;
+
+    fn add(self, other: Image) -> Image {
+        Image([0, 1, 2].map(|i| self.0[i] + other.0[i]))
+    }
+}
+
+impl Sub for Image {
+    type Output = Image;
+
+    fn sub(self, other: Image) -> Image {
+        Image([0, 1, 2].map(|i| self.0[i] - other.0[i]))
+    }
+}
+
+impl Neg for Image {
+    type Output = Image;
+
+    fn neg(self) -> Image {
+        Image(self.0.map(|x| -x))
+    }
+}
+
+impl AddAssign for Image {
+    fn add_assign(&mut self, other: Image) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Image {
+    fn sub_assign(&mut self, other: Image) {
+        *self = *self - other;
+    }
+}
+
+impl std::fmt::Display for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c] = self.0;
+        write!(f, "[{a}, {b}, {c}]")
+    }
+}
+// 9e4b7c2d ends here
+
+// [[file:../neighbors.note::*test][test:1]]
+#[test]
+fn test_image() {
+    let a = Image::new(1, 0, -1);
+    let b: Image = [0, 2, 1].into();
+    assert_eq!(a + b, Image::new(1, 2, 0));
+    assert_eq!(a - b, Image::new(1, -2, -2));
+    assert_eq!(-a + a, Image::ORIGIN);
+    assert!((a - a).is_origin());
+    assert!(b < a);
+    assert_eq!(a[2], -1);
+    assert_eq!(a.to_string(), "[1, 0, -1]");
+    assert_eq!(Image::from_vector(a.to_vector()), a);
+    assert_eq!(<[i32; 3]>::from(b), [0, 2, 1]);
+}
+// test:1 ends here
@@ -0,0 +1,229 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::backend::{bounding_box, SpatialIndex};
+use crate::base::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+// import:1 ends here
+
+// [[file:../neighbors.note::81f4c6d9][81f4c6d9]]
+/// The max number of points in each leaf node.
+const LEAF_SIZE: usize = 16;
+
+#[derive(Debug, Clone)]
+enum KdNode {
+    /// Points in range `start..end` of `KdTree.indices`.
+    Leaf { start: usize, end: usize },
+
+    /// Points in left child have coordinates along `axis` no larger than
+    /// `value`, and no smaller for right child.
+    Split {
+        axis: usize,
+        value: f64,
+        left: usize,
+        right: usize,
+    },
+}
+
+/// A static k-d tree for points in 3D space.
+#[derive(Debug, Clone)]
+pub(crate) struct KdTree {
+    points: Vec<Point>,
+    /// Point indices reordered for tree nodes.
+    indices: Vec<usize>,
+    nodes: Vec<KdNode>,
+    /// The lower and upper corners of bounding box of all points.
+    bounds: [Point; 2],
+}
+
+impl KdTree {
+    /// Build k-d tree from `points`.
+    pub fn new(points: &[Point]) -> Self {
+        let mut tree = Self {
+            points: points.to_vec(),
+            indices: (0..points.len()).collect(),
+            nodes: vec![],
+            bounds: bounding_box(points),
+        };
+        tree.build_node(0, points.len());
+        tree
+    }
+
+    /// Recursively build node for points in range `start..end`, returning
+    /// the node index.
+    fn build_node(&mut self, start: usize, end: usize) -> usize {
+        let inode = self.nodes.len();
+        if end - start <= LEAF_SIZE {
+            self.nodes.push(KdNode::Leaf { start, end });
+            return inode;
+        }
+
+        // split along the axis with the largest spread
+        let points = &self.points;
+        let slice = &mut self.indices[start..end];
+        let spread = |axis: usize| {
+            let (lo, hi) = slice.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &i| {
+                (lo.min(points[i][axis]), hi.max(points[i][axis]))
+            });
+            hi - lo
+        };
+        let axis = (0..3).max_by(|&a, &b| spread(a).total_cmp(&spread(b))).unwrap();
+        let mid = slice.len() / 2;
+        slice.select_nth_unstable_by(mid, |&i, &j| points[i][axis].total_cmp(&points[j][axis]));
+        let value = points[slice[mid]][axis];
+
+        // placeholder to be replaced after building children
+        self.nodes.push(KdNode::Leaf { start, end });
+        let left = self.build_node(start, start + mid);
+        let right = self.build_node(start + mid, end);
+        self.nodes[inode] = KdNode::Split { axis, value, left, right };
+        inode
+    }
+
+    fn distance2(&self, i: usize, pt: Point) -> f64 {
+        let p = self.points[i];
+        (0..3).map(|k| (p[k] - pt[k]).powi(2)).sum()
+    }
+
+    /// Call `visit` with point indices in each leaf node that may have
+    /// points with distances to `pt` in range `rmin..rmax`. Nodes outside
+    /// the outer sphere or inside the inner sphere are skipped.
+    fn visit_shell_leaves(&self, pt: Point, rmin: f64, rmax: f64, mut visit: impl FnMut(&[usize])) {
+        if self.points.is_empty() {
+            return;
+        }
+
+        let rmin2 = rmin * rmin;
+        let rmax2 = rmax * rmax;
+        // nodes to visit with their bounding boxes
+        let mut stack = vec![(0, self.bounds)];
+        while let Some((inode, [lo, hi])) = stack.pop() {
+            // skip the box outside the outer sphere
+            let dmin2: f64 = (0..3).map(|k| (lo[k] - pt[k]).max(pt[k] - hi[k]).max(0.0).powi(2)).sum();
+            if dmin2 >= rmax2 {
+                continue;
+            }
+            // skip the box inside the inner sphere
+            if rmin > 0.0 {
+                let dmax2: f64 = (0..3).map(|k| (pt[k] - lo[k]).powi(2).max((pt[k] - hi[k]).powi(2))).sum();
+                if dmax2 < rmin2 {
+                    continue;
+                }
+            }
+            match self.nodes[inode] {
+                KdNode::Leaf { start, end } => visit(&self.indices[start..end]),
+                KdNode::Split { axis, value, left, right } => {
+                    if pt[axis] - rmax <= value {
+                        let mut hi = hi;
+                        hi[axis] = value;
+                        stack.push((left, [lo, hi]));
+                    }
+                    if pt[axis] + rmax >= value {
+                        let mut lo = lo;
+                        lo[axis] = value;
+                        stack.push((right, [lo, hi]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Item in max-heap for k nearest neighbors search.
+#[derive(Debug, PartialEq)]
+struct Candidate(f64, usize);
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+impl SpatialIndex for KdTree {
+    fn search(&self, pt: Point, radius: f64) -> Vec<(usize, f64)> {
+        self.search_shell(pt, 0.0, radius)
+    }
+
+    fn search_shell(&self, pt: Point, rmin: f64, rmax: f64) -> Vec<(usize, f64)> {
+        let rmin2 = rmin * rmin;
+        let rmax2 = rmax * rmax;
+        let mut found = vec![];
+        self.visit_shell_leaves(pt, rmin, rmax, |indices| {
+            for &i in indices {
+                let d2 = self.distance2(i, pt);
+                if d2 < rmax2 && d2 >= rmin2 {
+                    found.push((i, d2.sqrt()));
+                }
+            }
+        });
+        found
+    }
+
+    fn nearest(&self, pt: Point, k: usize) -> Vec<(usize, f64)> {
+        if k == 0 || self.points.is_empty() {
+            return vec![];
+        }
+
+        let mut heap: BinaryHeap<Candidate> = BinaryHeap::with_capacity(k + 1);
+        // nodes to visit with lower bound of squared distance
+        let mut stack = vec![(0, 0.0)];
+        while let Some((inode, bound)) = stack.pop() {
+            if heap.len() == k && bound >= heap.peek().unwrap().0 {
+                continue;
+            }
+            match self.nodes[inode] {
+                KdNode::Leaf { start, end } => {
+                    for &i in &self.indices[start..end] {
+                        let d2 = self.distance2(i, pt);
+                        if heap.len() < k {
+                            heap.push(Candidate(d2, i));
+                        } else if d2 < heap.peek().unwrap().0 {
+                            heap.pop();
+                            heap.push(Candidate(d2, i));
+                        }
+                    }
+                }
+                KdNode::Split { axis, value, left, right } => {
+                    let diff = pt[axis] - value;
+                    let (near, far) = if diff <= 0.0 { (left, right) } else { (right, left) };
+                    // visit the near child first
+                    stack.push((far, bound.max(diff * diff)));
+                    stack.push((near, bound));
+                }
+            }
+        }
+
+        heap.into_sorted_vec().into_iter().map(|Candidate(d2, i)| (i, d2.sqrt())).collect()
+    }
+}
+// 81f4c6d9 ends here
+
+// [[file:../neighbors.note::*test][test:1]]
+#[test]
+fn test_kdtree_skip_inner_leaves() {
+    // points on a regular grid, with the query point at the center
+    let points: Vec<Point> = (0..8000).map(|i| [i % 20, (i / 20) % 20, i / 400].map(|x| x as f64)).collect();
+    let tree = KdTree::new(&points);
+    let pt = [9.6, 9.6, 9.6];
+    let leaves = |rmin: f64| {
+        let mut visited = vec![];
+        tree.visit_shell_leaves(pt, rmin, 8.0, |indices| visited.push(indices.to_vec()));
+        visited
+    };
+
+    let all = leaves(0.0);
+    let shell = leaves(7.0);
+    assert!(shell.len() < all.len());
+    // leaves with all points well inside the inner sphere are skipped
+    let inner = |x: &Vec<usize>| x.iter().all(|&i| tree.distance2(i, pt) < 4.0f64.powi(2));
+    assert!(all.iter().any(inner));
+    assert!(!shell.iter().any(inner));
+}
+// test:1 ends here
@@ -4,56 +4,146 @@
 
 // [[file:../neighbors.note::*mods][mods:1]]
 mod aperiodic;
+mod backend;
+mod batch;
+mod bonding;
+mod cells;
+mod clashes;
+mod cutoffs;
+mod dynamic;
+mod elements;
+mod error;
+mod exclusion;
+mod image;
+mod kdtree;
+mod list;
+mod nearest;
+mod pairs;
 mod periodic;
+mod verlet;
 // mods:1 ends here
 
 // [[file:../neighbors.note::8f73ed1e][8f73ed1e]]
 mod base {
+    use crate::backend::Backend;
+    use crate::dynamic::DynamicIndex;
+    use crate::exclusion::SelfExclusion;
+    use crate::image::Image;
     use indexmap::IndexMap;
     use lattice::Lattice;
-    use octree::Octree;
+    use std::fmt::Debug;
+    use std::hash::Hash;
     use vecfx::Vector3f;
 
     pub type Point = [f64; 3];
 
+    /// Types that can be used as the key of a point, such as `usize`,
+    /// `String`, tuples or UUIDs.
+    pub trait Key: Hash + Eq + Clone + Debug + Send + Sync {}
+
+    impl<T: Hash + Eq + Clone + Debug + Send + Sync> Key for T {}
+
     /// Helper struct for neighbors search result.
     #[derive(Debug, Clone, Copy)]
-    pub struct Neighbor {
+    pub struct Neighbor<K = usize> {
         /// The node connected to the host point.
-        pub node: usize,
+        pub node: K,
 
         /// The distance to the host point.
         pub distance: f64,
 
-        /// Scaled displacment vector relative to origin cell if PBC enabled.
-        pub image: Option<Vector3f>,
+        /// Lattice translation of the neighboring point relative to origin
+        /// cell if PBC enabled.
+        pub image: Option<Image>,
 
-        // Cartesian position of neighboring point
-        // pub position: Point,
+        /// Cartesian displacement vector from the host point to the
+        /// neighboring point (or its periodic image).
+        pub vector: Vector3f,
+
+        /// Cartesian position of neighboring point (or its periodic image).
+        pub position: Point,
+    }
+
+    /// The shortest displacement between two nodes, obeying the minimum image
+    /// convention if PBC enabled.
+    #[derive(Debug, Clone, Copy)]
+    pub struct MinimumImage {
+        /// The shortest distance between two nodes.
+        pub distance: f64,
+
+        /// Lattice translation of the closest image of the second node
+        /// relative to origin cell if PBC enabled.
+        pub image: Option<Image>,
+
+        /// Cartesian displacement vector from the first node to the closest
+        /// image of the second node.
+        pub vector: Vector3f,
     }
 
     /// Neighborhood is a neighboring nodes detector, for given cutoff distance.
-    #[derive(Debug, Clone, Default)]
-    pub struct Neighborhood {
+    #[derive(Debug, Clone)]
+    pub struct Neighborhood<K = usize> {
         /// particle coordinates
-        pub(crate) points: IndexMap<usize, Point>,
+        pub(crate) points: IndexMap<K, Point>,
 
-        /// Octree object
-        pub(crate) tree: Option<Octree>,
+        /// Spatial index for points
+        pub(crate) index: Option<DynamicIndex>,
 
         /// Periodic lattice.
         pub(crate) lattice: Option<Lattice>,
+
+        /// Periodic boundary conditions along three cell directions.
+        pub(crate) periodicity: [bool; 3],
+
+        /// Include periodic images of the host node in its neighbors.
+        pub(crate) self_images: bool,
+
+        /// Rule for excluding the host node from its neighbors.
+        pub(crate) self_exclusion: SelfExclusion,
+
+        /// Spatial index algorithm.
+        pub(crate) backend: Backend,
+
+        /// Cutoff radius for each node.
+        pub(crate) cutoffs: IndexMap<K, f64>,
+
+        /// The maximum cutoff radius of all nodes.
+        pub(crate) max_cutoff: f64,
+
+        /// Wrap points into the home cell in spatial index.
+        pub(crate) wrap: bool,
+    }
+
+    impl<K> Default for Neighborhood<K> {
+        fn default() -> Self {
+            Self {
+                points: IndexMap::new(),
+                index: None,
+                lattice: None,
+                periodicity: [true; 3],
+                self_images: true,
+                self_exclusion: SelfExclusion::default(),
+                backend: Backend::default(),
+                cutoffs: IndexMap::new(),
+                max_cutoff: 0.0,
+                wrap: false,
+            }
+        }
     }
 }
 // 8f73ed1e ends here
 
 // [[file:../neighbors.note::46a178d4][46a178d4]]
 mod api {
+    use crate::backend::Backend;
     use crate::base::*;
+    use crate::dynamic::DynamicIndex;
+    use crate::error::*;
     use lattice::Lattice;
-    use octree::Octree;
+    use std::borrow::Borrow;
+    use vecfx::Vector3f;
 
-    impl Neighborhood {
+    impl<K: Key> Neighborhood<K> {
         /// Constructs a neighborhood detector using the given `cutoff` distance.
         pub fn new() -> Self {
             Self { ..Default::default() }
@@ -63,29 +153,140 @@ mod api {
         /// iterator.
         ///
         /// The position of a point is associated with a permanent key in type
-        /// of `usize`.
+        /// of `K`, which is `usize` by default.
+        ///
+        /// Moving or inserting a few points is cheap: the spatial index is
+        /// updated incrementally, and only rebuilt when too many points
+        /// changed since last build.
         pub fn update<I>(&mut self, iter: I)
         where
-            I: IntoIterator<Item = (usize, Point)>,
+            I: IntoIterator<Item = (K, Point)>,
         {
             // update data points
+            let mut changed = vec![];
             for (k, v) in iter {
-                self.points.insert(k, v);
+                match self.points.insert_full(k, v) {
+                    (i, Some(old)) if old != v => changed.push((i, v, false)),
+                    (i, None) => changed.push((i, v, true)),
+                    _ => {}
+                }
+            }
+
+            match self.index.as_mut() {
+                Some(index) => {
+                    for (i, v, inserted) in changed {
+                        if inserted {
+                            index.inserted(i, v);
+                        } else {
+                            index.moved(i, v);
+                        }
+                    }
+                    self.rebuild_index_if_unbalanced();
+                }
+                None => self.rebuild_index(),
             }
-            // FIXME: how to reduce octree building
-            let points: Vec<_> = self.points.values().copied().collect();
-            let mut tree = Octree::new(points);
-            let bucket_size = 100;
-            tree.build(bucket_size);
-            self.tree = Some(tree);
         }
 
-        #[cfg(feature = "adhoc")]
-        /// Reset internal data.
+        /// Rebuild spatial index from scratch.
+        pub(crate) fn rebuild_index(&mut self) {
+            self.index = Some(DynamicIndex::build(&self.points, self.lattice, self.periodicity, self.backend, self.wrap));
+        }
+
+        /// Same as `update`, but with points in fractional coordinates of the
+        /// lattice, which could be outside the home cell, as read from CIF
+        /// files. Panics if there is no lattice.
+        pub fn update_fractional<I>(&mut self, iter: I)
+        where
+            I: IntoIterator<Item = (K, Point)>,
+        {
+            let lattice = self.lattice.expect("no lattice for fractional coordinates");
+            self.update(iter.into_iter().map(|(k, f)| (k, lattice.to_cart(f).into())));
+        }
+
+        /// Fallible version of `update_fractional`. Return an error if there
+        /// is no lattice, or any point has non-finite coordinates, leaving
+        /// Neighborhood untouched.
+        pub fn try_update_fractional<I>(&mut self, iter: I) -> Result<()>
+        where
+            I: IntoIterator<Item = (K, Point)>,
+        {
+            let lattice = self.lattice.ok_or(Error::NoLattice)?;
+            self.try_update(iter.into_iter().map(|(k, f)| (k, lattice.to_cart(f).into())))
+        }
+
+        /// Return true if spatial index has to be rebuilt for changed lattice
+        /// or periodicity.
+        fn index_uses_lattice(&self) -> bool {
+            (self.backend.uses_lattice() || self.wrap) && self.index.is_some()
+        }
+
+        /// Same as `update`, but check all points first. Neighborhood is left
+        /// untouched if any point has non-finite coordinates.
+        pub fn try_update<I>(&mut self, iter: I) -> Result<()>
+        where
+            I: IntoIterator<Item = (K, Point)>,
+        {
+            let items: Vec<_> = iter.into_iter().collect();
+            for &(_, p) in items.iter() {
+                check_point(p)?;
+            }
+            self.update(items);
+            Ok(())
+        }
+
+        /// Remove the point associated with key `n`. Return its position, or
+        /// None if there is no such point.
+        pub fn remove(&mut self, n: impl Borrow<K>) -> Option<Point> {
+            let p = self.remove_point(n.borrow());
+            self.rebuild_index_if_unbalanced();
+            p
+        }
+
+        /// Remove points associated with `keys`. Return the number of points
+        /// removed. Missing keys are ignored.
+        pub fn remove_many<I>(&mut self, keys: I) -> usize
+        where
+            I: IntoIterator,
+            I::Item: Borrow<K>,
+        {
+            let n = keys.into_iter().filter_map(|k| self.remove_point(k.borrow())).count();
+            self.rebuild_index_if_unbalanced();
+            n
+        }
+
+        /// Retain only the points specified by the predicate `f`.
+        pub fn retain<F>(&mut self, mut f: F)
+        where
+            F: FnMut(&K, &Point) -> bool,
+        {
+            let keys: Vec<_> = self.points.iter().filter(|(k, p)| !f(k, p)).map(|(k, _)| k.clone()).collect();
+            self.remove_many(keys);
+        }
+
+        fn remove_point(&mut self, n: &K) -> Option<Point> {
+            let (i, _, p) = self.points.swap_remove_full(n)?;
+            self.cutoffs.swap_remove(n);
+            if let Some(index) = self.index.as_mut() {
+                index.swap_removed(i);
+            }
+            Some(p)
+        }
+
+        fn rebuild_index_if_unbalanced(&mut self) {
+            if self.index.as_ref().is_some_and(|index| index.is_unbalanced()) {
+                self.rebuild_index();
+            }
+        }
+
+        /// Reset internal data, removing all points, cutoffs and lattice.
+        /// Settings such as periodicity, backend, wrapping and self
+        /// exclusion are kept.
         pub fn clear(&mut self) {
             self.points.clear();
+            self.cutoffs.clear();
+            self.max_cutoff = 0.0;
             self.lattice = None;
-            self.tree = None;
+            self.index = None;
         }
 
         /// Return an iterator of the nodes connected to the node `n`.
@@ -94,39 +295,59 @@ mod api {
         /// ----------
         /// * n: the key of host node for searching neighbors
         /// * radius: cutoff radius distance
-        pub fn neighbors(&self, n: usize, radius: f64) -> impl Iterator<Item = Neighbor> + '_ {
+        pub fn neighbors(&self, n: impl Borrow<K>, radius: f64) -> impl Iterator<Item = Neighbor<K>> + '_ {
             // the index of host node `n` in point list.
-            let (_, _, &pt) = self.points.get_full(&n).expect("invalid key");
+            let (_, n, &pt) = self.points.get_full(n.borrow()).expect("invalid key");
 
-            // FIXME: think twice
             // excluding self from the list
-            let epsilon = 1e-6;
-            self.search(pt, radius).filter_map(move |m| {
-                if m.node == n && m.distance < epsilon {
-                    return None;
-                }
-                Some(m)
-            })
+            self.search(pt, radius).filter(move |m| !self.is_excluded(n, m))
+        }
+
+        /// Return the nodes connected to the node `n` with distances in range
+        /// `rmin..rmax`, excluding the node `n` as in `neighbors`.
+        pub fn neighbors_in_shell(&self, n: impl Borrow<K>, rmin: f64, rmax: f64) -> impl Iterator<Item = Neighbor<K>> + '_ {
+            let (_, n, &pt) = self.points.get_full(n.borrow()).expect("invalid key");
+
+            self.search_shell(pt, rmin, rmax).filter(move |m| !self.is_excluded(n, m))
+        }
+
+        /// Fallible version of `neighbors`. Return an error instead of
+        /// panicking for an invalid key `n` or invalid search conditions.
+        pub fn try_neighbors(&self, n: impl Borrow<K>, radius: f64) -> Result<impl Iterator<Item = Neighbor<K>> + '_> {
+            let n = n.borrow();
+            let (_, n, &pt) = self.points.get_full(n).ok_or_else(|| Error::InvalidKey(format!("{n:?}")))?;
+
+            let iter = self.try_search(pt, radius)?.filter(move |m| !self.is_excluded(n, m));
+            Ok(iter)
         }
 
         /// Return the position of node `n`. Return None if there is no
         /// associated point with node `n`.
-        pub fn get_position(&self, n: usize) -> Option<&Point> {
-            self.points.get(&n)
+        pub fn get_position(&self, n: impl Borrow<K>) -> Option<&Point> {
+            self.points.get(n.borrow())
         }
 
         /// Return neighbors of a particle `pt` within distance cutoff `radius`.
-        pub fn search(&self, pt: Point, radius: f64) -> impl Iterator<Item = Neighbor> + '_ {
+        pub fn search(&self, pt: Point, radius: f64) -> impl Iterator<Item = Neighbor<K>> + '_ {
+            self.search_shell(pt, 0.0, radius)
+        }
+
+        /// Return neighbors of a particle `pt` with distances in range
+        /// `rmin..rmax`, for coordination shells or RDF binning. Regions
+        /// inside the inner sphere are skipped in spatial index, except for
+        /// octree (`Backend::Octree`, or `Backend::CellList` without lattice)
+        /// which filters them out after searching.
+        pub fn search_shell(&self, pt: Point, rmin: f64, rmax: f64) -> impl Iterator<Item = Neighbor<K>> + '_ {
             // inspired by: https://stackoverflow.com/a/54728634
             let mut iter_periodic = None;
             let mut iter_aperiodic = None;
             match self.lattice {
                 Some(lattice) => {
-                    let iter = self.search_neighbors_periodic(pt, radius, lattice);
+                    let iter = self.search_neighbors_periodic(pt, rmin, rmax, lattice);
                     iter_periodic = Some(iter);
                 }
                 None => {
-                    let iter = self.search_neighbors_aperiodic(pt, radius);
+                    let iter = self.search_neighbors_aperiodic(pt, rmin, rmax);
                     iter_aperiodic = Some(iter);
                 }
             }
@@ -136,6 +357,18 @@ mod api {
                 .chain(iter_aperiodic.into_iter().flatten())
         }
 
+        /// Fallible version of `search`. Return an error if `pt` or `radius`
+        /// is invalid, or the neighborhood is empty. Degenerate lattice is
+        /// rejected earlier by `try_set_lattice`.
+        pub fn try_search(&self, pt: Point, radius: f64) -> Result<impl Iterator<Item = Neighbor<K>> + '_> {
+            check_point(pt)?;
+            check_radius(radius)?;
+            if self.points.is_empty() || self.index.is_none() {
+                return Err(Error::EmptyNeighborhood);
+            }
+            Ok(self.search(pt, radius))
+        }
+
         /// Return current number of points.
         pub fn npoints(&self) -> usize {
             self.points.len()
@@ -146,12 +379,120 @@ mod api {
         pub fn set_lattice(&mut self, mat: [[f64; 3]; 3]) {
             let lat = Lattice::new(mat);
             self.lattice = Some(lat);
+            // cell list and wrapped points depend on lattice
+            if self.index_uses_lattice() {
+                self.rebuild_index();
+            }
+        }
+
+        /// Deform the lattice to `mat`, moving all points along with the cell
+        /// so that their fractional coordinates are kept, as in variable-cell
+        /// relaxations. For small strain, the spatial index is reused instead
+        /// of rebuilt. Same as `set_lattice` if there is no lattice yet.
+        pub fn deform_lattice(&mut self, mat: [[f64; 3]; 3]) {
+            let Some(old) = self.lattice else {
+                return self.set_lattice(mat);
+            };
+            let new = Lattice::new(mat);
+            // x -> new.to_cart(old.to_frac(x))
+            let m = new.matrix() * old.inv_matrix();
+            let shift = new.origin() - m * old.origin();
+            for p in self.points.values_mut() {
+                *p = (m * Vector3f::from(*p) + shift).into();
+            }
+            self.lattice = Some(new);
+            if let Some(index) = self.index.as_mut() {
+                index.deformed(m, shift);
+            }
+            self.rebuild_index_if_unbalanced();
+        }
+
+        /// Deform the lattice to `mat` and update points from an iterator
+        /// together, for frames of variable-cell trajectories. Points not in
+        /// `iter` move along with the cell as in `deform_lattice`.
+        pub fn update_with_lattice<I>(&mut self, mat: [[f64; 3]; 3], iter: I)
+        where
+            I: IntoIterator<Item = (K, Point)>,
+        {
+            self.deform_lattice(mat);
+            self.update(iter);
+        }
+
+        /// Remove the lattice, going back to aperiodic system. Points are kept
+        /// at their Cartesian positions.
+        pub fn remove_lattice(&mut self) {
+            self.lattice = None;
+            if self.index_uses_lattice() {
+                self.rebuild_index();
+            }
+        }
+
+        /// Set periodic boundary conditions along three cell directions, for
+        /// slabs (`[true, true, false]`) or wires (`[false, false, true]`).
+        /// Periodic images are only generated along periodic directions. The
+        /// default is periodic along all directions. This has no effect
+        /// without lattice.
+        pub fn set_periodicity(&mut self, pbc: [bool; 3]) {
+            self.periodicity = pbc;
+            // cell list and wrapped points depend on periodicity
+            if self.index_uses_lattice() {
+                self.rebuild_index();
+            }
+        }
+
+        /// Wrap points into the home cell along periodic directions in spatial
+        /// index, which speeds up searching for points far away from the
+        /// cell. Stored positions, and positions and images of neighbors, are
+        /// still relative to the original coordinates given in `update`. The
+        /// default is false.
+        pub fn set_wrap(&mut self, wrap: bool) {
+            self.wrap = wrap;
+            if self.index.is_some() {
+                self.rebuild_index();
+            }
+        }
+
+        /// Include periodic images of the host node itself in `neighbors`,
+        /// which happens when the cutoff is larger than the cell. The default
+        /// is true.
+        pub fn set_self_images(&mut self, include: bool) {
+            self.self_images = include;
+        }
+
+        /// Return periodic boundary conditions along three cell directions.
+        pub fn periodicity(&self) -> [bool; 3] {
+            self.periodicity
+        }
+
+        /// Set spatial index algorithm for neighbors search. The default is
+        /// `Backend::Octree`.
+        pub fn set_backend(&mut self, backend: Backend) {
+            self.backend = backend;
+            if self.index.is_some() {
+                self.rebuild_index();
+            }
+        }
+
+        /// Fallible version of `set_lattice`. Return an error for
+        /// degenerate lattice vectors instead of panicking.
+        pub fn try_set_lattice(&mut self, mat: [[f64; 3]; 3]) -> Result<()> {
+            check_lattice_vectors(mat)?;
+            self.set_lattice(mat);
+            Ok(())
         }
     }
 }
 // 46a178d4 ends here
 
 // [[file:../neighbors.note::*pub][pub:1]]
-pub use crate::api::*;
+pub use crate::backend::Backend;
 pub use crate::base::*;
+pub use crate::bonding::{Bonding, Radii};
+pub use crate::cutoffs::{Combine, CutoffRule};
+pub use crate::elements::{atomic_number, covalent_radius, element_symbol, vdw_radius, AsElement};
+pub use crate::error::{Error, Result};
+pub use crate::exclusion::SelfExclusion;
+pub use crate::image::Image;
+pub use crate::list::{CsrNeighborList, NeighborList};
+pub use crate::verlet::VerletList;
 // pub:1 ends here
@@ -0,0 +1,200 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use crate::image::Image;
+use indexmap::IndexSet;
+use std::borrow::Borrow;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::b7e2c4f9][b7e2c4f9]]
+/// Neighbors of all nodes in a neighborhood, stored contiguously.
+///
+/// Rows follow the insertion order of nodes, and neighbors in each row are
+/// sorted by distance, node and image, so the result is deterministic.
+#[derive(Debug, Clone)]
+pub struct NeighborList<K = usize> {
+    /// The host node of each row.
+    nodes: IndexSet<K>,
+
+    /// The start of neighbors in `neighbors` for each row.
+    offsets: Vec<usize>,
+
+    /// Neighbors of all rows.
+    neighbors: Vec<Neighbor<K>>,
+}
+
+impl<K: Key> NeighborList<K> {
+    fn from_rows(nodes: IndexSet<K>, rows: Vec<Vec<Neighbor<K>>>) -> Self {
+        let mut offsets = Vec::with_capacity(rows.len() + 1);
+        offsets.push(0);
+        for row in rows.iter() {
+            offsets.push(offsets.last().unwrap() + row.len());
+        }
+        let neighbors = rows.into_iter().flatten().collect();
+        Self {
+            nodes,
+            offsets,
+            neighbors,
+        }
+    }
+
+    /// Return the number of rows (host nodes).
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Return true if there is no row.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Return the total number of neighbors in all rows.
+    pub fn num_neighbors(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    /// Return an iterator over host nodes of all rows.
+    pub fn nodes(&self) -> impl Iterator<Item = &K> + '_ {
+        self.nodes.iter()
+    }
+
+    /// Return neighbors in row `i`.
+    pub fn row(&self, i: usize) -> &[Neighbor<K>] {
+        &self.neighbors[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    /// Return neighbors of node `n`, or None if there is no such node.
+    pub fn get(&self, n: impl Borrow<K>) -> Option<&[Neighbor<K>]> {
+        let i = self.nodes.get_index_of(n.borrow())?;
+        Some(self.row(i))
+    }
+
+    /// Return an iterator over host nodes and their neighbors.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &[Neighbor<K>])> + '_ {
+        self.nodes.iter().enumerate().map(move |(i, n)| (n, self.row(i)))
+    }
+}
+// b7e2c4f9 ends here
+
+// [[file:../neighbors.note::1c5d8e3b][1c5d8e3b]]
+impl<K: Key> Neighborhood<K> {
+    /// Return neighbors of all nodes within distance `cutoff`, as returned
+    /// by `neighbors`. Nodes are processed in parallel if the `rayon`
+    /// feature is enabled, and the result does not depend on the number of
+    /// threads.
+    pub fn neighbor_list(&self, cutoff: f64) -> NeighborList<K> {
+        let nodes: Vec<_> = self.points.keys().collect();
+        let search = |&n: &&K| {
+            let mut found: Vec<_> = self.neighbors(n, cutoff).collect();
+            self.sort_neighbors(&mut found);
+            found
+        };
+
+        #[cfg(feature = "rayon")]
+        let rows = nodes.par_iter().map(search).collect();
+        #[cfg(not(feature = "rayon"))]
+        let rows = nodes.iter().map(search).collect();
+
+        NeighborList::from_rows(nodes.into_iter().cloned().collect(), rows)
+    }
+}
+// 1c5d8e3b ends here
+
+// [[file:../neighbors.note::6d9a0b27][6d9a0b27]]
+/// Neighbor list in compressed sparse row (CSR) format, for sparse matrix
+/// code and graph neural networks.
+///
+/// Nodes are mapped to dense row indices in their insertion order. The
+/// neighbors of row `i` are stored in range `offsets[i]..offsets[i + 1]` of
+/// other arrays.
+#[derive(Debug, Clone)]
+pub struct CsrNeighborList<K = usize> {
+    keys: IndexSet<K>,
+    offsets: Vec<usize>,
+    indices: Vec<usize>,
+    distances: Vec<f64>,
+    images: Vec<Image>,
+    vectors: Vec<[f64; 3]>,
+}
+
+impl<K: Key> CsrNeighborList<K> {
+    /// Return the number of rows.
+    pub fn nrows(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Return the number of stored neighbors (edges).
+    pub fn nnz(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Return row offsets, with `nrows + 1` items.
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Return the row index of each neighbor.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Return the distance of each neighbor.
+    pub fn distances(&self) -> &[f64] {
+        &self.distances
+    }
+
+    /// Return the image of each neighbor. Always the origin cell for
+    /// aperiodic system.
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// Return the Cartesian displacement vector from host to each neighbor.
+    pub fn vectors(&self) -> &[[f64; 3]] {
+        &self.vectors
+    }
+
+    /// Return the node key of row `i`.
+    pub fn key(&self, i: usize) -> Option<&K> {
+        self.keys.get_index(i)
+    }
+
+    /// Return the row index of node `n`.
+    pub fn row_index(&self, n: impl Borrow<K>) -> Option<usize> {
+        self.keys.get_index_of(n.borrow())
+    }
+
+    /// Return edges in coordinate (COO) format as row indices of source
+    /// and target nodes.
+    pub fn to_coo(&self) -> [Vec<usize>; 2] {
+        let sources = (0..self.nrows())
+            .flat_map(|i| std::iter::repeat_n(i, self.offsets[i + 1] - self.offsets[i]))
+            .collect();
+        [sources, self.indices.clone()]
+    }
+}
+
+impl<K: Key> NeighborList<K> {
+    /// Convert to compressed sparse row format.
+    pub fn to_csr(&self) -> CsrNeighborList<K> {
+        let n = self.neighbors.len();
+        let mut csr = CsrNeighborList {
+            keys: self.nodes.clone(),
+            offsets: self.offsets.clone(),
+            indices: Vec::with_capacity(n),
+            distances: Vec::with_capacity(n),
+            images: Vec::with_capacity(n),
+            vectors: Vec::with_capacity(n),
+        };
+        for m in self.neighbors.iter() {
+            csr.indices.push(self.nodes.get_index_of(&m.node).expect("invalid node"));
+            csr.distances.push(m.distance);
+            csr.images.push(m.image.unwrap_or_default());
+            csr.vectors.push(m.vector.into());
+        }
+        csr
+    }
+}
+// 6d9a0b27 ends here
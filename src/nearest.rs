@@ -0,0 +1,172 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use crate::cells::images_within;
+use crate::error::*;
+use crate::image::Image;
+use std::borrow::Borrow;
+use std::f64::consts::PI;
+use vecfx::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::6f0b2d8a][6f0b2d8a]]
+/// Give up growing the search radius for nearest neighbors in periodic
+/// system after this many times.
+const MAX_GROWTH: usize = 64;
+
+impl<K: Key> Neighborhood<K> {
+    /// Return `k` nearest neighbors of a particle `pt`, sorted by distance.
+    ///
+    /// For periodic system, periodic images are taken into account, and the
+    /// same node may appear more than once if `k` is larger than the number
+    /// of points in the cell. For aperiodic system, at most `npoints` are
+    /// returned. Return nothing if `pt` has non-finite coordinates.
+    pub fn nearest(&self, pt: Point, k: usize) -> Vec<Neighbor<K>> {
+        if k == 0 || self.points.is_empty() || check_point(pt).is_err() {
+            return vec![];
+        }
+
+        match self.lattice {
+            Some(lattice) => {
+                // no periodic images to fill up
+                let k = if self.periodicity.contains(&true) {
+                    k
+                } else {
+                    k.min(self.npoints())
+                };
+
+                // initial guess of radius from average density of points
+                let n = self.npoints() as f64;
+                let volume = lattice.volume().abs();
+                let mut r = (0.75 * volume * k as f64 / (PI * n)).cbrt() * 1.2;
+                let mut found = vec![];
+                for _ in 0..MAX_GROWTH {
+                    found = self.search(pt, r).collect();
+                    if found.len() >= k {
+                        break;
+                    }
+                    r *= 1.5;
+                }
+                self.sort_neighbors(&mut found);
+                found.truncate(k);
+                found
+            }
+            None => {
+                let index = self.index.as_ref().expect("octree not ready");
+                index
+                    .nearest(&self.points, pt, k)
+                    .into_iter()
+                    .map(|(index, distance)| {
+                        let (node, &position) = self.points.get_index(index).expect("invalid index");
+                        Neighbor {
+                            node: node.clone(),
+                            distance,
+                            image: None,
+                            vector: Vector3f::from(position) - Vector3f::from(pt),
+                            position,
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Fallible version of `nearest`. Return an error if `pt` is invalid, or
+    /// the neighborhood is empty.
+    pub fn try_nearest(&self, pt: Point, k: usize) -> Result<Vec<Neighbor<K>>> {
+        check_point(pt)?;
+        if self.points.is_empty() || self.index.is_none() {
+            return Err(Error::EmptyNeighborhood);
+        }
+        Ok(self.nearest(pt, k))
+    }
+
+    /// Return `k` nearest neighbors of the node `n`, sorted by distance. The
+    /// node `n` itself is excluded following `set_self_exclusion`, but not
+    /// its periodic images.
+    pub fn nearest_neighbors(&self, n: impl Borrow<K>, k: usize) -> Vec<Neighbor<K>> {
+        let n = n.borrow();
+        let &pt = self.points.get(n).expect("invalid key");
+
+        // more nodes could be excluded for distance tolerance
+        let mut nsearch = k + 1;
+        loop {
+            let mut found = self.nearest(pt, nsearch);
+            let complete = found.len() < nsearch;
+            found.retain(|m| !self.is_self(n, m));
+            if found.len() >= k || complete {
+                found.truncate(k);
+                return found;
+            }
+            nsearch *= 2;
+        }
+    }
+}
+
+impl<K: Key> Neighborhood<K> {
+    /// Return the closest neighbor of a particle `pt`, or None if there is
+    /// no point in neighborhood.
+    pub fn closest(&self, pt: Point) -> Option<Neighbor<K>> {
+        self.nearest(pt, 1).into_iter().next()
+    }
+
+    /// Return the shortest displacement from node `i` to node `j`, obeying
+    /// the minimum image convention if PBC enabled. Return None if there is
+    /// no point associated with `i` or `j`.
+    pub fn minimum_image(&self, i: impl Borrow<K>, j: impl Borrow<K>) -> Option<MinimumImage> {
+        let pi: Vector3f = (*self.points.get(i.borrow())?).into();
+        let pj: Vector3f = (*self.points.get(j.borrow())?).into();
+        let d = pj - pi;
+        let mic = match self.lattice {
+            Some(lattice) if self.periodicity != [true; 3] => {
+                // a good guess from rounding along periodic directions, and
+                // then check all images within that distance
+                let pbc = self.periodicity;
+                let df = lattice.inv_matrix() * d;
+                let guess = Vector3f::from([0, 1, 2].map(|k| if pbc[k] { -df[k].round() } else { 0.0 }));
+                let r = (d + lattice.matrix() * guess).norm() * (1.0 + 1e-8) + 1e-8;
+                let (distance, image) = images_within(&lattice, pbc, pi.into(), pj.into(), r)
+                    .into_iter()
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .expect("no image found");
+                MinimumImage {
+                    distance,
+                    image: Some(image),
+                    vector: d + lattice.matrix() * image.to_vector(),
+                }
+            }
+            Some(lattice) => {
+                let vector = lattice.apply_mic(d);
+                let image = Image::from_vector(lattice.inv_matrix() * (vector - d));
+                MinimumImage {
+                    distance: vector.norm(),
+                    image: Some(image),
+                    vector,
+                }
+            }
+            None => MinimumImage {
+                distance: d.norm(),
+                image: None,
+                vector: d,
+            },
+        };
+        Some(mic)
+    }
+
+    /// Return the shortest distance between node `i` and node `j`, obeying
+    /// the minimum image convention if PBC enabled. Return None if there is
+    /// no point associated with `i` or `j`.
+    pub fn distance(&self, i: impl Borrow<K>, j: impl Borrow<K>) -> Option<f64> {
+        self.minimum_image(i, j).map(|mic| mic.distance)
+    }
+
+    /// Sort neighbors by distance, using insertion order of nodes and image
+    /// to break ties for reproducible results.
+    pub(crate) fn sort_neighbors(&self, neighbors: &mut [Neighbor<K>]) {
+        // the bits of non-negative floats have the same order
+        neighbors.sort_by_cached_key(|m| {
+            let i = self.points.get_index_of(&m.node).expect("invalid node");
+            (m.distance.to_bits(), i, m.image)
+        });
+    }
+}
+// 6f0b2d8a ends here
@@ -0,0 +1,43 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use crate::image::Image;
+// import:1 ends here
+
+// [[file:../neighbors.note::9c4e1b7d][9c4e1b7d]]
+impl<K: Key> Neighborhood<K> {
+    /// Return an iterator over all pairs of nodes within distance `cutoff`,
+    /// visiting each pair only once (half neighbor list).
+    ///
+    /// Each item is a tuple of host node and its neighbor. The pair `(i, j,
+    /// image)` is the same as `(j, i, -image)`, and only the one with host
+    /// node `i` inserted before `j` is reported. For periodic images of the
+    /// same node (`i == j`), only the one with positive image is reported,
+    /// comparing image components in order of a, b, c, unless self images
+    /// are excluded by `set_self_images`.
+    pub fn all_pairs(&self, cutoff: f64) -> impl Iterator<Item = (K, Neighbor<K>)> + '_ {
+        self.points
+            .keys()
+            .enumerate()
+            .flat_map(move |(i, n)| self.half_neighbors(i, cutoff).map(move |m| (n.clone(), m)))
+    }
+
+    /// Return neighbors of the node with index `i` within distance `cutoff`,
+    /// in the half neighbor list convention of `all_pairs`.
+    pub(crate) fn half_neighbors(&self, i: usize, cutoff: f64) -> impl Iterator<Item = Neighbor<K>> + '_ {
+        let (_, &pt) = self.points.get_index(i).expect("invalid index");
+        self.search(pt, cutoff).filter(move |m| {
+            let j = self.points.get_index_of(&m.node).expect("invalid node");
+            match i.cmp(&j) {
+                std::cmp::Ordering::Less => true,
+                std::cmp::Ordering::Greater => false,
+                std::cmp::Ordering::Equal => self.self_images && m.image.is_some_and(is_positive_image),
+            }
+        })
+    }
+}
+
+/// Return true if the first nonzero component of `image` is positive.
+fn is_positive_image(image: Image) -> bool {
+    image.0.iter().find(|&&x| x != 0).is_some_and(|&x| x > 0)
+}
+// 9c4e1b7d ends here
@@ -1,24 +1,53 @@
 // [[file:../neighbors.note::*import][import:1]]
 use crate::base::*;
+use crate::image::Image;
 use lattice::Lattice;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 use vecfx::*;
 // import:1 ends here
 
 // [[file:../neighbors.note::c17f484d][c17f484d]]
-impl Neighborhood {
-    /// Search neighbors for periodic system.
-    pub(crate) fn search_neighbors_periodic(&self, pt: Point, cutoff: f64, lattice: Lattice) -> impl Iterator<Item = Neighbor> + '_ {
-        // the minimum supercell size ranges
-        let relevant_cell_sizes: Vec<_> = lattice
-            .widths()
-            .iter()
-            .map(|&w| {
-                let rc = cutoff / w;
-                let a = (-rc).floor() as isize;
-                let b = (1.0 + rc).ceil() as isize;
-                [a, b]
-            })
-            .collect();
+impl<K: Key> Neighborhood<K> {
+    /// Search neighbors for periodic system, with distances in range
+    /// `rmin..cutoff`.
+    pub(crate) fn search_neighbors_periodic(&self, pt: Point, rmin: f64, cutoff: f64, lattice: Lattice) -> impl Iterator<Item = Neighbor<K>> + '_ {
+        let iter_cells = self.search_neighbors_cells(pt, rmin, cutoff, lattice);
+        let mut iter_images = None;
+        if iter_cells.is_none() {
+            let images = ImageRanges::new(self, lattice);
+            iter_images = Some(self.search_neighbors_images(pt, rmin, cutoff, images));
+        }
+        iter_cells.into_iter().flatten().chain(iter_images.into_iter().flatten())
+    }
+
+    /// Search neighbors using spatial index supporting periodic search
+    /// natively, such as linked-cell binning. Return None if not supported.
+    fn search_neighbors_cells(&self, pt: Point, rmin: f64, cutoff: f64, lattice: Lattice) -> Option<impl Iterator<Item = Neighbor<K>> + '_> {
+        let index = self.index.as_ref()?;
+        let mat = lattice.matrix();
+        let found = index.search_periodic(&self.points, pt, rmin, cutoff, lattice, self.periodicity)?;
+        let iter = found.map(move |(index, distance, image)| {
+            let (node, &p) = self.points.get_index(index).expect("invalid index");
+            let position = Vector3f::from(p) + mat * image.to_vector();
+            Neighbor {
+                node: node.clone(),
+                distance,
+                image: Some(image),
+                vector: position - Vector3f::from(pt),
+                position: position.into(),
+            }
+        });
+        Some(iter)
+    }
+
+    /// Search neighbors by running octree queries over periodic images.
+    pub(crate) fn search_neighbors_images(&self, pt: Point, rmin: f64, cutoff: f64, images: ImageRanges) -> impl Iterator<Item = Neighbor<K>> + '_ {
+        let index = self.index.as_ref().expect("octree not ready.");
+        let lattice = images.lattice;
+        let mat = lattice.matrix();
+        let [ra, rb, rc] = images.ranges(pt, cutoff);
 
         // to avoid octree building for each image, we mirror the query points
         // and then mirror back
@@ -28,30 +57,90 @@ impl Neighborhood {
         // let pt = lattice.wrap(pt);
         let pt: Vector3f = pt.into();
         let pt_images = lattice
-            .replicate(
-                relevant_cell_sizes[0][0]..=relevant_cell_sizes[0][1],
-                relevant_cell_sizes[1][0]..=relevant_cell_sizes[1][1],
-                relevant_cell_sizes[2][0]..=relevant_cell_sizes[2][1],
-            )
+            .replicate(ra, rb, rc)
             .map(move |tv| {
                 let new_pt = pt + lattice.to_cart(tv);
-                (new_pt, -tv)
+                (new_pt, -Image::from_vector(tv))
             });
 
         // run queries over all relevant images
-        let tree = self.tree.as_ref().expect("octree not ready.");
-        pt_images.flat_map(move |(pt, image): (Vector3f, Vector3f)| {
-            tree.search(pt.into(), cutoff).into_iter().map(move |(index, distance)| {
-                let (&node, _) = self.points.get_index(index).expect("invalid index");
+        pt_images.flat_map(move |(pt_image, image): (Vector3f, Image)| {
+            // translate back from the image of query point
+            let offset = pt - pt_image;
+            index.search_shell(&self.points, pt_image.into(), rmin, cutoff).map(move |(index, distance, shift)| {
+                let (node, &p) = self.points.get_index(index).expect("invalid index");
+                // the found point could be wrapped from another image
+                let position = Vector3f::from(p) + offset + mat * shift.to_vector();
                 Neighbor {
-                    node,
+                    node: node.clone(),
                     distance,
-                    image: Some(image),
+                    image: Some(image + shift),
+                    vector: position - pt,
+                    position: position.into(),
                 }
             })
         })
     }
 }
+
+/// Relevant images of query points for searching neighbors, with data
+/// shared by many queries precomputed.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ImageRanges {
+    lattice: Lattice,
+    widths: [f64; 3],
+    /// Extents of all points in fractional coordinates. None if there is no
+    /// point.
+    fbounds: Option<[Vector3f; 2]>,
+    pbc: [bool; 3],
+}
+
+impl ImageRanges {
+    pub fn new<K>(nh: &Neighborhood<K>, lattice: Lattice) -> Self {
+        let index = nh.index.as_ref().expect("octree not ready.");
+        Self {
+            lattice,
+            widths: lattice.widths(),
+            fbounds: index.bounds().map(|bounds| fractional_extents(&lattice, bounds)),
+            pbc: nh.periodicity,
+        }
+    }
+
+    /// Return the ranges of lattice translations of query point `pt`. Points
+    /// may be located outside the unit cell, so we use the extents of all
+    /// points in fractional coordinates, and the cutoff may be larger than
+    /// the cell.
+    fn ranges(&self, pt: Point, cutoff: f64) -> [RangeInclusive<isize>; 3] {
+        let fq = self.lattice.to_frac(pt);
+        [0, 1, 2].map(|i| match self.fbounds {
+            Some([fmin, fmax]) if self.pbc[i] => {
+                let rc = cutoff / self.widths[i];
+                let a = (fmin[i] - fq[i] - rc).floor() as isize;
+                let b = (fmax[i] - fq[i] + rc).ceil() as isize;
+                a..=b
+            }
+            // no images along non-periodic direction, or no points at all
+            _ => 0..=0,
+        })
+    }
+}
+
+/// Return the lower and upper extents in fractional coordinates of a box in
+/// Cartesian space.
+fn fractional_extents(lattice: &Lattice, bounds: [Point; 2]) -> [Vector3f; 2] {
+    let [lo, hi] = bounds;
+    let mut fmin = Vector3f::from([f64::INFINITY; 3]);
+    let mut fmax = Vector3f::from([f64::NEG_INFINITY; 3]);
+    for i in 0..8 {
+        let corner = [0, 1, 2].map(|k| if i & (1 << k) == 0 { lo[k] } else { hi[k] });
+        let f = lattice.to_frac(corner);
+        for k in 0..3 {
+            fmin[k] = fmin[k].min(f[k]);
+            fmax[k] = fmax[k].max(f[k]);
+        }
+    }
+    [fmin, fmax]
+}
 // c17f484d ends here
 
 // [[file:../neighbors.note::*triclinic][triclinic:1]]
@@ -193,3 +282,46 @@ fn test_orthorhombic() {
     }
 }
 // orthorhombic:1 ends here
+
+// [[file:../neighbors.note::5a0e3f67][5a0e3f67]]
+impl<K: Key> Neighborhood<K> {
+    /// Return the neighboring nodes of node `n` within distance `radius`,
+    /// collapsing periodic images of the same node. Each item is a tuple of
+    /// neighboring node and its multiplicity (the number of its images
+    /// within `radius`), in insertion order of nodes.
+    pub fn neighbors_with_multiplicity(&self, n: impl Borrow<K>, radius: f64) -> Vec<(K, usize)> {
+        let mut counts = HashMap::new();
+        for m in self.neighbors(n, radius) {
+            let i = self.points.get_index_of(&m.node).expect("invalid node");
+            *counts.entry(i).or_insert(0) += 1;
+        }
+        let mut collapsed: Vec<_> = counts.into_iter().collect();
+        collapsed.sort_unstable();
+        collapsed
+            .into_iter()
+            .map(|(i, count)| (self.points.get_index(i).expect("invalid index").0.clone(), count))
+            .collect()
+    }
+
+    /// Return neighbors of a particle `pt` within distance `radius`, each
+    /// node reported only once at its closest image, sorted by distance.
+    pub fn search_unique(&self, pt: Point, radius: f64) -> Vec<Neighbor<K>> {
+        let mut found: Vec<_> = self.search(pt, radius).collect();
+        self.sort_neighbors(&mut found);
+        let mut visited = HashSet::new();
+        found.retain(|m| visited.insert(m.node.clone()));
+        found
+    }
+
+    /// Return the nodes connected to the node `n` within distance `radius`,
+    /// each node reported only once at its closest image, sorted by
+    /// distance. The node `n` itself and its periodic images are excluded.
+    pub fn neighbors_unique(&self, n: impl Borrow<K>, radius: f64) -> Vec<Neighbor<K>> {
+        let n = n.borrow();
+        let &pt = self.points.get(n).expect("invalid key");
+        let mut found = self.search_unique(pt, radius);
+        found.retain(|m| &m.node != n);
+        found
+    }
+}
+// 5a0e3f67 ends here
@@ -0,0 +1,128 @@
+// [[file:../neighbors.note::*import][import:1]]
+use crate::base::*;
+use indexmap::IndexMap;
+use std::borrow::Borrow;
+use vecfx::*;
+// import:1 ends here
+
+// [[file:../neighbors.note::a4d8e3b6][a4d8e3b6]]
+/// Verlet neighbor list for molecular dynamics.
+///
+/// Neighbors are searched within `cutoff + skin` when the list is built. The
+/// list stays valid until some point moved more than `skin / 2` since last
+/// build, and neighbors within `cutoff` can be read from the list without
+/// searching again.
+#[derive(Debug, Clone)]
+pub struct VerletList<K = usize> {
+    nh: Neighborhood<K>,
+    cutoff: f64,
+    skin: f64,
+
+    /// Current positions of points.
+    positions: IndexMap<K, Point>,
+
+    /// Candidate neighbors within `cutoff + skin` at last build.
+    candidates: IndexMap<K, Vec<Neighbor<K>>>,
+}
+
+impl<K: Key> VerletList<K> {
+    /// Build Verlet neighbor list for all points in `nh`.
+    ///
+    /// Parameters
+    /// ----------
+    /// * nh: the neighborhood with points and optional lattice
+    /// * cutoff: cutoff radius distance for neighbors
+    /// * skin: extra distance for searching candidate neighbors
+    pub fn new(nh: Neighborhood<K>, cutoff: f64, skin: f64) -> Self {
+        assert!(cutoff >= 0.0 && skin >= 0.0, "invalid cutoff or skin: {cutoff}, {skin}");
+        let mut vl = Self {
+            positions: nh.points.clone(),
+            nh,
+            cutoff,
+            skin,
+            candidates: IndexMap::new(),
+        };
+        vl.rebuild();
+        vl
+    }
+
+    /// Update positions of points. The list will be rebuilt if any point
+    /// moved more than half of the skin distance since last build, or if
+    /// there is a new point. Return true if rebuilt.
+    pub fn update<I>(&mut self, iter: I) -> bool
+    where
+        I: IntoIterator<Item = (K, Point)>,
+    {
+        let mut outdated = false;
+        for (k, v) in iter {
+            match self.nh.points.get(&k) {
+                Some(p) => {
+                    let d: Vector3f = Vector3f::from(v) - Vector3f::from(*p);
+                    if d.norm() > 0.5 * self.skin {
+                        outdated = true;
+                    }
+                }
+                None => outdated = true,
+            }
+            self.positions.insert(k, v);
+        }
+
+        if outdated {
+            self.nh.update(self.positions.iter().map(|(k, &v)| (k.clone(), v)));
+            self.rebuild();
+        }
+        outdated
+    }
+
+    /// Search candidate neighbors again for all points.
+    fn rebuild(&mut self) {
+        let r = self.cutoff + self.skin;
+        self.candidates = self
+            .nh
+            .points
+            .keys()
+            .map(|k| (k.clone(), self.nh.neighbors(k, r).collect()))
+            .collect();
+    }
+
+    /// Return an iterator of the nodes connected to the node `n` within
+    /// cutoff distance, using current positions.
+    pub fn neighbors(&self, n: impl Borrow<K>) -> impl Iterator<Item = Neighbor<K>> + '_ {
+        let n = n.borrow();
+        let pn: Vector3f = self.positions.get(n).copied().expect("invalid key").into();
+        let candidates = &self.candidates[n];
+        candidates.iter().filter_map(move |m| {
+            let mut pm: Vector3f = self.positions[&m.node].into();
+            if let (Some(lattice), Some(image)) = (self.nh.lattice, m.image) {
+                pm += lattice.matrix() * image.to_vector();
+            }
+            let distance = (pm - pn).norm();
+            if distance < self.cutoff {
+                Some(Neighbor {
+                    distance,
+                    vector: pm - pn,
+                    position: pm.into(),
+                    ..m.clone()
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Return the cutoff radius distance.
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    /// Return the skin distance.
+    pub fn skin(&self) -> f64 {
+        self.skin
+    }
+
+    /// Return a reference to the neighborhood at last build.
+    pub fn neighborhood(&self) -> &Neighborhood<K> {
+        &self.nh
+    }
+}
+// a4d8e3b6 ends here
@@ -0,0 +1,84 @@
+// [[file:../neighbors.note::5c0d8e72][5c0d8e72]]
+use gchemol_neighbors::{Backend, Neighbor, Neighborhood};
+
+mod common;
+use common::{frac_to_cart, lcg, neighbor_summary, BACKENDS};
+
+#[test]
+fn test_backends_against_brute_force() {
+    let a = 9.0;
+    let cell = [[a, 0.0, 0.0], [1.5, a, 0.0], [-2.0, 0.5, a]];
+    let mut seed = 17;
+    let points: Vec<_> = (0..200).map(|_| frac_to_cart(cell, [lcg(&mut seed), lcg(&mut seed), lcg(&mut seed)])).collect();
+
+    for periodic in [false, true] {
+        let build = |backend| {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().copied().enumerate());
+            if periodic {
+                nh.set_lattice(cell);
+            }
+            nh
+        };
+
+        let reference = build(Backend::BruteForce);
+        for backend in BACKENDS {
+            let nh = build(backend);
+            for i in (0..points.len()).step_by(7) {
+                for cutoff in [1.5, 3.0, 7.0] {
+                    assert_eq!(
+                        neighbor_summary(reference.neighbors(i, cutoff)),
+                        neighbor_summary(nh.neighbors(i, cutoff)),
+                        "{backend:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_nearest_against_brute_force() {
+    let mut seed = 1;
+    let points: Vec<_> = (0..500).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 10.0)).collect();
+    let build = |backend| {
+        let mut nh = Neighborhood::new();
+        nh.set_backend(backend);
+        nh.update(points.iter().copied().enumerate());
+        nh
+    };
+
+    let reference = build(Backend::BruteForce);
+    let distances = |found: Vec<Neighbor>| -> Vec<_> { found.into_iter().map(|m| m.distance).collect() };
+    for backend in BACKENDS {
+        let nh = build(backend);
+        for &q in &points[..50] {
+            for k in [1, 12, 600] {
+                assert_eq!(distances(reference.nearest(q, k)), distances(nh.nearest(q, k)), "{backend:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_coincident_points() {
+    // all points are located at the query point
+    let p = [1.0, 2.0, 3.0];
+    for backend in BACKENDS {
+        let mut nh = Neighborhood::new();
+        nh.set_backend(backend);
+        nh.update([(0, p)]);
+        let found = nh.nearest(p, 1);
+        assert_eq!(found.len(), 1, "{backend:?}");
+        assert_eq!((found[0].node, found[0].distance), (0, 0.0), "{backend:?}");
+        assert!(nh.nearest_neighbors(0, 1).is_empty(), "{backend:?}");
+
+        nh.update([(1, p)]);
+        let found = nh.nearest_neighbors(0, 1);
+        assert_eq!(found.len(), 1, "{backend:?}");
+        assert_eq!((found[0].node, found[0].distance), (1, 0.0), "{backend:?}");
+        assert_eq!(nh.nearest(p, 3).len(), 2, "{backend:?}");
+    }
+}
+// 5c0d8e72 ends here
@@ -0,0 +1,39 @@
+// [[file:../neighbors.note::0a7c3e5f][0a7c3e5f]]
+use gchemol_neighbors::Neighborhood;
+
+mod common;
+use common::{lcg, neighbor_summary, BACKENDS};
+
+#[test]
+fn test_search_many() {
+    let cell = [[8.0, 0.0, 0.0], [1.0, 7.0, 0.0], [-1.5, 0.5, 9.0]];
+    let mut seed = 13;
+    let points: Vec<_> = (0..200).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 9.0 - 0.5)).collect();
+    // probe points on a grid
+    let grid: Vec<_> = (0..125)
+        .map(|i| [(i % 5) as f64 * 2.0, ((i / 5) % 5) as f64 * 2.0, (i / 25) as f64 * 2.0])
+        .collect();
+
+    for backend in BACKENDS {
+        for periodic in [false, true] {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().copied().enumerate());
+            if periodic {
+                nh.set_lattice(cell);
+            }
+            for radius in [1.5, 4.0] {
+                let found = nh.search_many(&grid, radius);
+                assert_eq!(found.len(), grid.len());
+                for (&pt, found) in grid.iter().zip(found) {
+                    assert_eq!(neighbor_summary(found), neighbor_summary(nh.search(pt, radius)), "{backend:?}");
+                }
+            }
+        }
+    }
+
+    let nh = Neighborhood::<usize>::new();
+    assert_eq!(nh.search_many(&grid, 1.0).len(), grid.len());
+    assert!(nh.search_many(&[], 1.0).is_empty());
+}
+// 0a7c3e5f ends here
@@ -0,0 +1,51 @@
+// [[file:../neighbors.note::62d1f0a8][62d1f0a8]]
+use gchemol_neighbors::{Bonding, Error, Radii};
+
+#[test]
+fn test_bonding_molecule() {
+    // methane
+    let atoms = vec![
+        (1, "C", [0.0000, 0.0000, 0.0000]),
+        (2, "H", [0.6291, 0.6291, 0.6291]),
+        (3, "H", [-0.6291, -0.6291, 0.6291]),
+        (4, "H", [-0.6291, 0.6291, -0.6291]),
+        (5, "H", [0.6291, -0.6291, -0.6291]),
+    ];
+    let mut bonding = Bonding::new();
+    bonding.update(atoms).unwrap();
+    assert_eq!(bonding.get_element(1), Some(6));
+
+    let bonds = bonding.bonds();
+    assert_eq!(bonds.len(), 4);
+    assert!(bonds.iter().all(|(i, _)| *i == 1));
+    assert_eq!(bonding.bonded(2).count(), 1);
+    assert_eq!(bonding.bonded(1).count(), 4);
+
+    // H-H distance 1.78 is less than the sum of vdW radii
+    bonding.set_radii(Radii::VanDerWaals);
+    assert_eq!(bonding.bonds().len(), 10);
+    bonding.set_radii(Radii::Covalent);
+    bonding.set_tolerance(0.0);
+    assert_eq!(bonding.bonds().len(), 0);
+
+    // invalid element
+    let r = bonding.update(vec![(6, "Xy", [1.0, 1.0, 1.0])]);
+    assert_eq!(r, Err(Error::InvalidElement("\"Xy\"".into())));
+    assert_eq!(bonding.neighborhood().npoints(), 5);
+}
+
+#[test]
+fn test_bonding_periodic() {
+    // primitive cell of rock salt NaCl, using atomic numbers
+    let a = 5.64;
+    let cell = [[0.0, a / 2.0, a / 2.0], [a / 2.0, 0.0, a / 2.0], [a / 2.0, a / 2.0, 0.0]];
+    let mut bonding = Bonding::new();
+    bonding.update(vec![(0, 11, [0.0; 3]), (1, 17, [a / 2.0; 3])]).unwrap();
+    bonding.set_lattice(cell);
+
+    let bonds = bonding.bonds();
+    assert_eq!(bonds.len(), 6);
+    assert!(bonds.iter().all(|(i, m)| *i == 0 && m.node == 1 && (m.distance - a / 2.0).abs() < 1e-6));
+    assert_eq!(bonding.bonded(1).count(), 6);
+}
+// 62d1f0a8 ends here
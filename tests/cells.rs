@@ -0,0 +1,38 @@
+// [[file:../neighbors.note::b6c2e9f4][b6c2e9f4]]
+use gchemol_neighbors::{Backend, Neighborhood};
+
+mod common;
+use common::{frac_to_cart, lcg, neighbor_summary, SKEWED_CELL};
+
+#[test]
+fn test_cell_list_backend() {
+    let cell = SKEWED_CELL;
+
+    // random points in the unit cell
+    let mut seed = 5;
+    let points: Vec<_> = (0..100).map(|_| frac_to_cart(cell, [lcg(&mut seed), lcg(&mut seed), lcg(&mut seed)])).collect();
+
+    let mut nh1 = Neighborhood::new();
+    nh1.update(points.iter().copied().enumerate());
+    nh1.set_lattice(cell);
+
+    let mut nh2 = Neighborhood::new();
+    nh2.set_backend(Backend::CellList);
+    nh2.update(points.iter().copied().enumerate());
+    nh2.set_lattice(cell);
+
+    for &cutoff in &[1.0, 2.5, 6.0, 11.0] {
+        for i in 0..points.len() {
+            assert_eq!(neighbor_summary(nh1.neighbors(i, cutoff)), neighbor_summary(nh2.neighbors(i, cutoff)));
+        }
+    }
+
+    // the same after moving a few points
+    let moved: Vec<_> = (0..5).map(|i| (i * 7, [lcg(&mut seed) * 5.0, lcg(&mut seed) * 5.0, lcg(&mut seed) * 5.0])).collect();
+    nh1.update(moved.clone());
+    nh2.update(moved);
+    for i in 0..points.len() {
+        assert_eq!(neighbor_summary(nh1.neighbors(i, 4.0)), neighbor_summary(nh2.neighbors(i, 4.0)));
+    }
+}
+// b6c2e9f4 ends here
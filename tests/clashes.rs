@@ -0,0 +1,65 @@
+// [[file:../neighbors.note::6a1f3b9e][6a1f3b9e]]
+use gchemol_neighbors::{Image, Neighborhood};
+
+mod common;
+use common::lcg;
+
+#[test]
+fn test_clashes() {
+    let cell = [[8.0, 0.0, 0.0], [0.0, 8.0, 0.0], [0.0, 0.0, 8.0]];
+    let mut seed = 31;
+    let points: Vec<_> = (0..120).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 8.0)).collect();
+
+    for periodic in [false, true] {
+        let mut nh = Neighborhood::new();
+        nh.update(points.iter().copied().enumerate());
+        if periodic {
+            nh.set_lattice(cell);
+        }
+
+        let min_distance = 0.8;
+        let clashes = nh.clashes(min_distance);
+        let mut expected: Vec<_> = nh.all_pairs(min_distance).map(|(i, m)| (i, m.node, m.image)).collect();
+        expected.sort();
+        let mut found: Vec<_> = clashes.iter().map(|(i, m)| (*i, m.node, m.image)).collect();
+        found.sort();
+        assert!(!found.is_empty());
+        assert_eq!(found, expected);
+        assert!(clashes.windows(2).all(|w| w[0].1.distance <= w[1].1.distance));
+
+        // early exit on any clash
+        let (i, m) = nh.find_clash(min_distance).unwrap();
+        assert!(clashes.iter().any(|(n, x)| *n == i && x.node == m.node && x.image == m.image));
+        assert!(!nh.is_clash_free(min_distance));
+
+        // no clash below the shortest distance
+        let dmin = clashes[0].1.distance;
+        assert!(nh.is_clash_free(dmin));
+        assert!(nh.find_clash(dmin).is_none());
+        assert!(nh.clashes(dmin).is_empty());
+    }
+}
+
+#[test]
+fn test_clashes_periodic() {
+    // two atoms clash only across the cell boundary
+    let cell = [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 5.0]];
+    let mut nh = Neighborhood::new();
+    nh.update([(0, [0.2, 2.0, 2.0]), (1, [4.7, 2.0, 2.0])]);
+    assert!(nh.is_clash_free(1.0));
+
+    nh.set_lattice(cell);
+    let clashes = nh.clashes(1.0);
+    assert_eq!(clashes.len(), 1);
+    let (n, m) = &clashes[0];
+    assert_eq!((*n, m.node, m.image), (0, 1, Some(Image::new(-1, 0, 0))));
+    assert!((m.distance - 0.5).abs() < 1e-8);
+
+    // an atom clashing with its own image in a tiny cell
+    nh.update([(2, [1.0, 1.0, 1.0])]);
+    nh.set_lattice([[0.9, 0.0, 0.0], [0.0, 9.0, 0.0], [0.0, 0.0, 9.0]]);
+    assert!(nh.clashes(1.0).iter().any(|(n, m)| *n == 2 && m.node == 2));
+
+    assert!(Neighborhood::<usize>::new().is_clash_free(1.0));
+}
+// 6a1f3b9e ends here
@@ -0,0 +1,35 @@
+// [[file:../../neighbors.note::e4b7c2d9][e4b7c2d9]]
+// shared helpers for integration tests; each test crate uses only some of them
+#![allow(dead_code)]
+
+use gchemol_neighbors::{Backend, Image, Neighbor};
+
+/// All available backends.
+pub const BACKENDS: [Backend; 4] = [Backend::Octree, Backend::KdTree, Backend::CellList, Backend::BruteForce];
+
+/// A simple pseudo random number generator for reproducible tests, returning
+/// numbers in range `0..1`.
+pub fn lcg(seed: &mut u64) -> f64 {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*seed >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// A strongly skewed triclinic cell.
+#[rustfmt::skip]
+pub const SKEWED_CELL: [[f64; 3]; 3] = [[ 8.60700000e+00,  0.00000000e+00,  0.00000000e+00],
+                                        [ 8.64636107e-04,  4.95399992e+00,  0.00000000e+00],
+                                        [-3.14318359e+00,  1.38078488e-02,  6.91625732e+00]];
+
+/// Sorted `(node, image, distance)` of found neighbors for comparison, with
+/// distances rounded to 1e-6.
+pub fn neighbor_summary(found: impl IntoIterator<Item = Neighbor>) -> Vec<(usize, Option<Image>, i64)> {
+    let mut items: Vec<_> = found.into_iter().map(|m| (m.node, m.image, (m.distance * 1e6).round() as i64)).collect();
+    items.sort();
+    items
+}
+
+/// Convert fractional coordinates `f` into Cartesian coordinates in cell `mat`.
+pub fn frac_to_cart(mat: [[f64; 3]; 3], f: [f64; 3]) -> [f64; 3] {
+    [0, 1, 2].map(|x| (0..3).map(|y| f[y] * mat[y][x]).sum())
+}
+// e4b7c2d9 ends here
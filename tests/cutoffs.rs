@@ -0,0 +1,38 @@
+// [[file:../neighbors.note::8e2f7c14][8e2f7c14]]
+use gchemol_neighbors::{Combine, Neighborhood};
+
+#[test]
+fn test_per_node_cutoffs() {
+    // O-H-H ... C
+    let points = [[0.0, 0.0, 0.0], [0.96, 0.0, 0.0], [-0.24, 0.93, 0.0], [2.6, 0.0, 0.0]];
+    let radii = [0.66, 0.31, 0.31, 0.76];
+    let mut nh = Neighborhood::new();
+    nh.update(points.iter().copied().enumerate());
+    nh.set_cutoffs(radii.iter().copied().enumerate());
+    assert_eq!(nh.get_cutoff(3), 0.76);
+
+    let sorted = |iter: &mut dyn Iterator<Item = usize>| {
+        let mut nodes: Vec<_> = iter.collect();
+        nodes.sort();
+        nodes
+    };
+
+    // O-H bonds: 0.96 < 0.66 + 0.31; H-H: 1.53 > 0.62
+    assert_eq!(sorted(&mut nh.neighbors_with_cutoffs(0, Combine::Sum).map(|m| m.node)), vec![1, 2]);
+    assert_eq!(sorted(&mut nh.neighbors_with_cutoffs(1, Combine::Sum).map(|m| m.node)), vec![0]);
+    // 1.64 > 0.31 + 0.76
+    assert!(nh.neighbors_with_cutoffs(3, Combine::Sum).next().is_none());
+    // 1.64 < 0.31 + 0.76 + 0.6
+    let rule = |ri: f64, rj: f64| ri + rj + 0.6;
+    assert_eq!(sorted(&mut nh.neighbors_with_cutoffs(3, rule).map(|m| m.node)), vec![1]);
+    // max rule
+    assert!(nh.neighbors_with_cutoffs(0, Combine::Max).next().is_none());
+    nh.set_cutoffs(vec![(0, 1.0)]);
+    assert_eq!(sorted(&mut nh.neighbors_with_cutoffs(0, Combine::Max).map(|m| m.node)), vec![1, 2]);
+
+    // pairs
+    let mut pairs: Vec<_> = nh.all_pairs_with_cutoffs(rule).map(|(i, m)| (i, m.node)).collect();
+    pairs.sort();
+    assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 3)]);
+}
+// 8e2f7c14 ends here
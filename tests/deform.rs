@@ -0,0 +1,121 @@
+// [[file:../neighbors.note::7b2e4f90][7b2e4f90]]
+use gchemol_neighbors::{Image, Neighborhood};
+use vecfx::*;
+
+mod common;
+use common::{frac_to_cart, lcg, neighbor_summary, BACKENDS};
+
+#[test]
+fn test_deform_lattice() {
+    let cell = [[8.0, 0.0, 0.0], [1.0, 7.5, 0.0], [-1.5, 0.5, 9.0]];
+    let mut seed = 5;
+    let fracs: Vec<_> = (0..200).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 1.2 - 0.1)).collect();
+
+    // small strain reusing the index, then large strain beyond rebuild
+    let strains = [0.01, 0.03, -0.02, 0.3];
+    for pbc in [[true; 3], [true, true, false]] {
+        for backend in BACKENDS {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.set_periodicity(pbc);
+            nh.update(fracs.iter().map(|&f| frac_to_cart(cell, f)).enumerate());
+            nh.set_lattice(cell);
+
+            let mut mat = cell;
+            for (k, e) in strains.into_iter().enumerate() {
+                // shear and stretch
+                mat[0][0] *= 1.0 + e;
+                mat[1][0] += 5.0 * e;
+                mat[2][2] *= 1.0 - e;
+                nh.deform_lattice(mat);
+
+                let mut expected = Neighborhood::new();
+                expected.set_backend(backend);
+                expected.set_periodicity(pbc);
+                expected.update(fracs.iter().map(|&f| frac_to_cart(mat, f)).enumerate());
+                expected.set_lattice(mat);
+
+                for i in (0..fracs.len()).step_by(7) {
+                    let p = nh.get_position(i).unwrap();
+                    assert!((Vector3f::from(*p) - Vector3f::from(frac_to_cart(mat, fracs[i]))).norm() < 1e-8);
+                    assert_eq!(
+                        neighbor_summary(nh.neighbors(i, 3.5)),
+                        neighbor_summary(expected.neighbors(i, 3.5)),
+                        "{backend:?}, strain {k}"
+                    );
+                }
+                let pt = [1.0, 2.0, 3.0];
+                assert_eq!(neighbor_summary(nh.nearest(pt, 5)), neighbor_summary(expected.nearest(pt, 5)), "{backend:?}");
+            }
+
+            // going back to aperiodic system
+            nh.remove_lattice();
+            let mut expected = Neighborhood::new();
+            expected.update(fracs.iter().map(|&f| frac_to_cart(mat, f)).enumerate());
+            for i in (0..fracs.len()).step_by(11) {
+                assert_eq!(neighbor_summary(nh.neighbors(i, 3.5)), neighbor_summary(expected.neighbors(i, 3.5)), "{backend:?}");
+            }
+            assert!(nh.neighbors(0, 3.5).all(|m| m.image.is_none()));
+        }
+    }
+}
+
+#[test]
+fn test_update_with_lattice() {
+    let cell = [[6.0, 0.0, 0.0], [0.0, 6.0, 0.0], [0.0, 0.0, 6.0]];
+    let mut nh = Neighborhood::new();
+    nh.update([(0, [0.5, 0.5, 0.5]), (1, [5.0, 0.5, 0.5]), (2, [3.0, 3.0, 3.0])]);
+    nh.set_lattice(cell);
+    assert_eq!(nh.neighbors(0, 2.0).count(), 1);
+
+    // compress the cell and move point 2 close to point 0 together
+    let cell = [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 5.0]];
+    nh.update_with_lattice(cell, [(2, [1.5, 0.5, 0.5])]);
+    let p = nh.get_position(1).unwrap();
+    assert!((Vector3f::from(*p) - Vector3f::from([5.0 / 1.2, 0.5 / 1.2, 0.5 / 1.2])).norm() < 1e-8);
+    let found: Vec<_> = nh.neighbors(2, 1.2).map(|m| m.node).collect();
+    assert_eq!(found, vec![0]);
+    let m = nh.neighbors(0, 1.3).find(|m| m.node == 1).unwrap();
+    assert_eq!(m.image, Some(Image::new(-1, 0, 0)));
+
+    // same as set_lattice without lattice
+    nh.remove_lattice();
+    assert_eq!(nh.neighbors(0, 1.3).count(), 1);
+    nh.deform_lattice(cell);
+    assert_eq!(nh.neighbors(0, 1.3).count(), 2);
+}
+
+#[test]
+fn test_nearest_after_deform() {
+    let cell = [[6.0, 0.0, 0.0], [0.0, 6.0, 0.0], [0.0, 0.0, 6.0]];
+    let mut seed = 11;
+    let points: Vec<_> = (0..20).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 6.0)).collect();
+
+    for scale in [[1.03; 3], [1.02, 0.99, 1.04]] {
+        for backend in BACKENDS {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().copied().enumerate());
+            nh.set_lattice(cell);
+            // the index is kept under strain after the lattice removed
+            nh.deform_lattice([0, 1, 2].map(|i| cell[i].map(|x| x * scale[i])));
+            nh.remove_lattice();
+
+            let mut fresh = Neighborhood::new();
+            fresh.set_backend(backend);
+            fresh.update((0..points.len()).map(|i| (i, *nh.get_position(i).unwrap())));
+            for k in 1..=6 {
+                for i in 0..points.len() {
+                    let p = *nh.get_position(i).unwrap();
+                    assert_eq!(neighbor_summary(nh.nearest(p, k)), neighbor_summary(fresh.nearest(p, k)), "{backend:?}");
+                    assert_eq!(
+                        neighbor_summary(nh.nearest_neighbors(i, k)),
+                        neighbor_summary(fresh.nearest_neighbors(i, k)),
+                        "{backend:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+// 7b2e4f90 ends here
@@ -0,0 +1,28 @@
+// [[file:../neighbors.note::9d2c41aa][9d2c41aa]]
+use gchemol_neighbors::{Error, Neighborhood};
+
+#[test]
+fn test_fallible_api() {
+    let mut nh = Neighborhood::new();
+    // search before update
+    assert_eq!(nh.try_search([0.0; 3], 1.0).err(), Some(Error::EmptyNeighborhood));
+
+    let points = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.2, 0.0]];
+    nh.update(points.iter().enumerate().map(|(i, &v)| (i, v)));
+    assert_eq!(nh.try_neighbors(0, 1.1).unwrap().count(), 1);
+    assert_eq!(nh.try_neighbors(3, 1.1).err(), Some(Error::InvalidKey("3".into())));
+    assert_eq!(nh.try_search([0.0; 3], -1.0).err(), Some(Error::InvalidRadius(-1.0)));
+    assert!(matches!(nh.try_search([f64::NAN, 0.0, 0.0], 1.0), Err(Error::NonFiniteCoordinates(_))));
+
+    // neighborhood is not changed for bad points
+    let r = nh.try_update(vec![(3, [1.0, 1.0, 1.0]), (4, [f64::INFINITY, 0.0, 0.0])]);
+    assert!(matches!(r, Err(Error::NonFiniteCoordinates(_))));
+    assert_eq!(nh.npoints(), 3);
+
+    let cell = [[2.0, 0.0, 0.0], [4.0, 0.0, 0.0], [0.0, 0.0, 3.0]];
+    assert_eq!(nh.try_set_lattice(cell), Err(Error::DegenerateLattice));
+    let cell = [[2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 3.0]];
+    assert!(nh.try_set_lattice(cell).is_ok());
+    assert!(nh.try_neighbors(0, 1.1).is_ok());
+}
+// 9d2c41aa ends here
@@ -0,0 +1,82 @@
+// [[file:../neighbors.note::d8c3e6a2][d8c3e6a2]]
+use gchemol_neighbors::{Neighborhood, SelfExclusion};
+
+fn nodes(nh: &Neighborhood, n: usize, radius: f64) -> Vec<usize> {
+    let mut nodes: Vec<_> = nh.neighbors(n, radius).map(|m| m.node).collect();
+    nodes.sort();
+    nodes
+}
+
+#[test]
+fn test_self_exclusion() {
+    // point 1 is a disordered site coincident with point 0
+    let points = [(0, [0.0; 3]), (1, [0.0; 3]), (2, [1.0, 0.0, 0.0]), (3, [0.05, 0.0, 0.0])];
+    let mut nh = Neighborhood::new();
+    nh.update(points);
+
+    assert_eq!(nodes(&nh, 0, 1.5), vec![1, 2, 3]);
+    assert_eq!(nh.neighbors_in_shell(0, 0.0, 1.5).count(), 3);
+    assert_eq!(nh.nearest_neighbors(0, 1)[0].node, 1);
+
+    nh.set_self_exclusion(SelfExclusion::Tolerance(0.1));
+    assert_eq!(nodes(&nh, 0, 1.5), vec![2]);
+    assert_eq!(nodes(&nh, 2, 1.5), vec![0, 1, 3]);
+    assert_eq!(nh.nearest_neighbors(0, 1)[0].node, 2);
+    assert_eq!(nh.nearest_neighbors(0, 3).len(), 1);
+
+    nh.set_self_exclusion(SelfExclusion::Include);
+    assert_eq!(nodes(&nh, 0, 1.5), vec![0, 1, 2, 3]);
+    assert_eq!(nh.try_neighbors(0, 1.5).unwrap().count(), 4);
+    // all pairs are not affected
+    assert_eq!(nh.all_pairs(1.5).count(), 6);
+}
+
+#[test]
+fn test_self_exclusion_periodic() {
+    let cell = [[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 3.0]];
+    let mut nh = Neighborhood::new();
+    nh.update([(0, [0.0; 3])]);
+    nh.set_lattice(cell);
+
+    for (rule, n, m) in [
+        (SelfExclusion::Identity, 6, 0),
+        (SelfExclusion::Tolerance(1e-6), 6, 0),
+        (SelfExclusion::Include, 7, 1),
+        // images of the host within tolerance are excluded as well
+        (SelfExclusion::Tolerance(3.5), 0, 0),
+    ] {
+        nh.set_self_exclusion(rule);
+        nh.set_self_images(true);
+        assert_eq!(nh.neighbors(0, 3.1).count(), n, "{rule:?}");
+        nh.set_self_images(false);
+        assert_eq!(nh.neighbors(0, 3.1).count(), m, "{rule:?}");
+    }
+
+    // self images of host are kept in nearest neighbors
+    nh.set_self_exclusion(SelfExclusion::Identity);
+    assert_eq!(nh.nearest_neighbors(0, 6).len(), 6);
+    assert!(nh.nearest_neighbors(0, 6).iter().all(|m| m.distance == 3.0));
+}
+
+#[test]
+fn test_overlapping_sites() {
+    let cell = [[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]];
+    let points = [
+        (0, [0.0, 5.0, 5.0]),
+        (1, [3.0, 3.0, 3.0]),
+        // across the cell boundary
+        (2, [9.95, 5.0, 5.0]),
+        (3, [6.0, 6.0, 6.0]),
+        // a chain of close sites
+        (4, [3.0, 3.0, 3.08]),
+        (5, [3.0, 3.0, 3.16]),
+    ];
+    let mut nh = Neighborhood::new();
+    nh.update(points);
+    assert_eq!(nh.overlapping_sites(0.1), vec![vec![1, 4, 5]]);
+
+    nh.set_lattice(cell);
+    assert_eq!(nh.overlapping_sites(0.1), vec![vec![0, 2], vec![1, 4, 5]]);
+    assert_eq!(nh.overlapping_sites(0.01), Vec::<Vec<usize>>::new());
+}
+// d8c3e6a2 ends here
@@ -0,0 +1,104 @@
+// [[file:../neighbors.note::a4d61c3e][a4d61c3e]]
+use gchemol_neighbors::{Backend, Error, Image, Neighborhood};
+use std::collections::HashMap;
+use vecfx::*;
+
+mod common;
+use common::{frac_to_cart, lcg, neighbor_summary, BACKENDS};
+
+#[test]
+fn test_wrap_points() {
+    let cell = [[7.0, 0.0, 0.0], [-1.0, 6.5, 0.0], [1.5, 0.5, 8.0]];
+    let mut seed = 17;
+    // fractional coordinates far outside the home cell
+    let fracs: Vec<_> = (0..150).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 5.0 - 2.0)).collect();
+    let moves: Vec<_> = (0..150).step_by(13).map(|i| (i, [0, 1, 2].map(|_| lcg(&mut seed) * 7.0 - 3.0))).collect();
+
+    for pbc in [[true; 3], [true, false, true]] {
+        // skip brute force: far too slow for points spread over many cells
+        for backend in BACKENDS.into_iter().filter(|&b| b != Backend::BruteForce) {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.set_periodicity(pbc);
+            nh.set_lattice(cell);
+            nh.update_fractional(fracs.iter().copied().enumerate());
+            let mut wrapped = nh.clone();
+            wrapped.set_wrap(true);
+
+            // positions are kept as given
+            for (i, &f) in fracs.iter().enumerate() {
+                let p = Vector3f::from(frac_to_cart(cell, f));
+                assert!((Vector3f::from(*wrapped.get_position(i).unwrap()) - p).norm() < 1e-8);
+            }
+
+            let check = |nh: &Neighborhood, wrapped: &Neighborhood| {
+                for i in (0..fracs.len()).step_by(5) {
+                    let expected = neighbor_summary(nh.neighbors(i, 4.0));
+                    assert_eq!(neighbor_summary(wrapped.neighbors(i, 4.0)), expected, "{backend:?}");
+                    // at the same image positions
+                    let positions: HashMap<_, _> = nh.neighbors(i, 4.0).map(|m| ((m.node, m.image), m.position)).collect();
+                    for m in wrapped.neighbors(i, 4.0) {
+                        let p = positions[&(m.node, m.image)];
+                        assert!((Vector3f::from(m.position) - Vector3f::from(p)).norm() < 1e-6, "{backend:?}");
+                    }
+                }
+                let pt = [20.0, -13.0, 30.0];
+                assert_eq!(wrapped.search(pt, 3.0).count(), nh.search(pt, 3.0).count(), "{backend:?}");
+                let mic = |nh: &Neighborhood| nh.minimum_image(0, 1).map(|mic| (mic.image, (mic.distance * 1e6).round()));
+                assert_eq!(mic(wrapped), mic(nh));
+            };
+            check(&nh, &wrapped);
+
+            // moved points are not wrapped until rebuild
+            for nh in [&mut nh, &mut wrapped] {
+                nh.update_fractional(moves.iter().copied());
+            }
+            check(&nh, &wrapped);
+
+            // strained cell keeps wrapped points in the home cell
+            let mut mat = cell;
+            mat[0][0] *= 1.02;
+            for nh in [&mut nh, &mut wrapped] {
+                nh.deform_lattice(mat);
+            }
+            check(&nh, &wrapped);
+
+            wrapped.set_wrap(false);
+            check(&nh, &wrapped);
+        }
+    }
+}
+
+#[test]
+fn test_update_fractional() {
+    let cell = [[4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]];
+    let mut nh = Neighborhood::new();
+    nh.set_lattice(cell);
+    nh.set_wrap(true);
+    nh.update_fractional([(0, [0.1, 0.1, 0.1]), (1, [-1.8, 0.1, 0.1])]);
+    assert_eq!(nh.get_position(1), Some(&[-7.2, 0.4, 0.4]));
+
+    // the image of point 1 next to point 0, relative to its given position
+    let m = nh.neighbors(0, 1.0).next().unwrap();
+    assert_eq!(m.node, 1);
+    assert_eq!(m.image, Some(Image::new(2, 0, 0)));
+    assert!((m.distance - 0.4).abs() < 1e-8);
+    let m = nh.neighbors(1, 1.0).next().unwrap();
+    assert_eq!(m.image, Some(Image::new(-2, 0, 0)));
+    assert!((m.vector[0] + 0.4).abs() < 1e-8);
+}
+
+#[test]
+fn test_try_update_fractional() {
+    let mut nh = Neighborhood::new();
+    assert_eq!(nh.try_update_fractional([(0, [0.1, 0.1, 0.1])]), Err(Error::NoLattice));
+    assert_eq!(nh.npoints(), 0);
+
+    nh.set_lattice([[4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]]);
+    assert!(nh.try_update_fractional([(0, [0.1, 0.1, 0.1])]).is_ok());
+    assert_eq!(nh.get_position(0), Some(&[0.4, 0.4, 0.4]));
+    let r = nh.try_update_fractional([(1, [0.5, 0.5, 0.5]), (2, [f64::NAN, 0.0, 0.0])]);
+    assert!(matches!(r, Err(Error::NonFiniteCoordinates(_))));
+    assert_eq!(nh.npoints(), 1);
+}
+// a4d61c3e ends here
@@ -0,0 +1,191 @@
+// [[file:../neighbors.note::8b1f6d3a][8b1f6d3a]]
+use gchemol_neighbors::{Image, Neighborhood};
+use vecfx::*;
+
+mod common;
+use common::{frac_to_cart, lcg, neighbor_summary, BACKENDS, SKEWED_CELL};
+
+#[test]
+fn test_fcc_shells() {
+    // primitive cell of fcc Cu, much smaller than cutoffs below
+    let a = 3.61;
+    let cell = [[0.0, a / 2.0, a / 2.0], [a / 2.0, 0.0, a / 2.0], [a / 2.0, a / 2.0, 0.0]];
+    // coordination numbers of the first six shells
+    let shells = [
+        (a / 2f64.sqrt(), 12),
+        (a, 18),
+        (a * 1.5f64.sqrt(), 42),
+        (a * 2f64.sqrt(), 54),
+        (a * 2.5f64.sqrt(), 78),
+        (a * 3f64.sqrt(), 86),
+    ];
+
+    // the atom placed far away from the origin cell
+    let [va, vb, vc] = cell.map(Vector3f::from);
+    let far = 3.0 * va - 2.0 * vc + 5.0 * vb;
+    for backend in BACKENDS {
+        for p in [[0.0; 3], far.into()] {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update([(0, p)]);
+            nh.set_lattice(cell);
+            for (r, n) in shells {
+                assert_eq!(nh.neighbors(0, r + 0.01).count(), n, "{backend:?}");
+                assert_eq!(nh.neighbors_with_multiplicity(0, r + 0.01), vec![(0, n)], "{backend:?}");
+            }
+
+            // excluding self images
+            nh.set_self_images(false);
+            assert_eq!(nh.neighbors(0, 10.0).count(), 0, "{backend:?}");
+            assert_eq!(nh.all_pairs(10.0).count(), 0, "{backend:?}");
+            assert!(nh.neighbors_with_multiplicity(0, 10.0).is_empty());
+            // images still found by search
+            assert_eq!(nh.search(p, a + 0.01).count(), 19, "{backend:?}");
+        }
+    }
+}
+
+#[test]
+fn test_images_outside_cell() {
+    let cell = SKEWED_CELL;
+    let [va, vb, vc] = cell.map(Vector3f::from);
+
+    // random points in fractional range of [-1.5, 2.5)
+    let mut seed = 11;
+    let points: Vec<Vector3f> = (0..20)
+        .map(|_| frac_to_cart(cell, [0, 1, 2].map(|_| lcg(&mut seed) * 4.0 - 1.5)).into())
+        .collect();
+
+    for cutoff in [3.0, 9.0] {
+        // brute force over enough images
+        let nmax = 7;
+        let mut expected = vec![];
+        for (i, &pi) in points.iter().enumerate() {
+            let mut found = vec![];
+            for (j, &pj) in points.iter().enumerate() {
+                for a in -nmax..=nmax {
+                    for b in -nmax..=nmax {
+                        for c in -nmax..=nmax {
+                            let image = Image::new(a, b, c);
+                            let d = (pj + a as f64 * va + b as f64 * vb + c as f64 * vc - pi).norm();
+                            if d < cutoff && !(i == j && image.is_origin()) {
+                                found.push((j, Some(image), (d * 1e6).round() as i64));
+                            }
+                        }
+                    }
+                }
+            }
+            found.sort();
+            expected.push(found);
+        }
+
+        for backend in BACKENDS {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().map(|&p| p.into()).enumerate());
+            nh.set_lattice(cell);
+            for (i, expected) in expected.iter().enumerate() {
+                assert_eq!(&neighbor_summary(nh.neighbors(i, cutoff)), expected, "{backend:?}, cutoff = {cutoff}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_unique_neighbors() {
+    let cell = SKEWED_CELL;
+    let mut seed = 3;
+    let points: Vec<_> = (0..40).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 8.0 - 1.0)).collect();
+
+    for periodic in [false, true] {
+        for backend in BACKENDS {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().copied().enumerate());
+            if periodic {
+                nh.set_lattice(cell);
+            }
+            for i in 0..points.len() {
+                for cutoff in [2.0, 5.0, 12.0] {
+                    let found = nh.neighbors_unique(i, cutoff);
+                    // each node at its minimum image
+                    let mut expected: Vec<_> = (0..points.len())
+                        .filter(|&j| j != i)
+                        .filter_map(|j| nh.minimum_image(i, j).filter(|mic| mic.distance < cutoff).map(|mic| (j, mic.distance)))
+                        .collect();
+                    expected.sort_by_key(|x| x.0);
+                    let mut nodes: Vec<_> = found.iter().map(|m| (m.node, m.distance)).collect();
+                    nodes.sort_by_key(|x| x.0);
+                    assert_eq!(nodes.len(), expected.len(), "{backend:?}");
+                    for (x, y) in nodes.iter().zip(expected.iter()) {
+                        assert_eq!(x.0, y.0);
+                        assert!((x.1 - y.1).abs() < 1e-8);
+                    }
+                    assert!(found.windows(2).all(|w| w[0].distance <= w[1].distance));
+                }
+            }
+        }
+    }
+}
+
+/// Neighbor pairs generated by scripts/test_ase_neighborlist.py.
+struct AsePairs {
+    cutoff: f64,
+    cell: [[f64; 3]; 3],
+    points: Vec<[f64; 3]>,
+    /// Sorted pairs of `(i, j, image, distance)`.
+    pairs: Vec<(usize, usize, Image, f64)>,
+}
+
+fn read_ase_pairs(path: &str) -> AsePairs {
+    let txt = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{path}: {e}"));
+    let mut cutoff = 0.0;
+    let mut cell = vec![];
+    let mut points = vec![];
+    let mut pairs = vec![];
+    for line in txt.lines().filter(|l| !l.starts_with('#')) {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match fields[0] {
+            "cutoff" => cutoff = fields[1].parse().unwrap(),
+            "cell" => cell.push([1, 2, 3].map(|k| fields[k].parse::<f64>().unwrap())),
+            "point" => points.push([2, 3, 4].map(|k| fields[k].parse::<f64>().unwrap())),
+            "pair" => {
+                let [i, j] = [1, 2].map(|k| fields[k].parse::<usize>().unwrap());
+                let [ia, ib, ic] = [3, 4, 5].map(|k| fields[k].parse::<i32>().unwrap());
+                let d: f64 = fields[6].parse().unwrap();
+                pairs.push((i, j, Image::new(ia, ib, ic), d));
+            }
+            _ => panic!("invalid line: {line}"),
+        }
+    }
+    pairs.sort_by_key(|x| (x.0, x.1, x.2));
+    let cell = cell.try_into().unwrap();
+    AsePairs { cutoff, cell, points, pairs }
+}
+
+#[test]
+#[ignore = "requires reference files generated with ASE by scripts/test_ase_neighborlist.py"]
+fn test_ase_reference() {
+    // primitive fcc cell and skewed cell, with cutoff larger than the cell
+    // and points outside the cell
+    for path in ["tests/files/ase-fcc-pairs.txt", "tests/files/ase-skewed-pairs.txt"] {
+        let AsePairs { cutoff, cell, points, pairs: expected } = read_ase_pairs(path);
+        assert!(!expected.is_empty());
+        for backend in BACKENDS {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().copied().enumerate());
+            nh.set_lattice(cell);
+            let mut found: Vec<_> = (0..points.len())
+                .flat_map(|i| nh.neighbors(i, cutoff).map(move |m| (i, m.node, m.image.unwrap(), m.distance)))
+                .collect();
+            found.sort_by_key(|x| (x.0, x.1, x.2));
+            assert_eq!(found.len(), expected.len(), "{path}: {backend:?}");
+            for (x, y) in found.iter().zip(expected.iter()) {
+                assert_eq!((x.0, x.1, x.2), (y.0, y.1, y.2), "{path}: {backend:?}");
+                assert!((x.3 - y.3).abs() < 1e-8, "{path}: {backend:?}");
+            }
+        }
+    }
+}
+// 8b1f6d3a ends here
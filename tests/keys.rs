@@ -0,0 +1,58 @@
+// [[file:../neighbors.note::e3a9c5d1][e3a9c5d1]]
+use gchemol_neighbors::{Bonding, Neighborhood};
+
+#[test]
+fn test_string_keys() {
+    // atoms identified by (residue, atom name)
+    let key = |res: usize, name: &str| (res, name.to_string());
+    let points = vec![
+        (key(1, "O"), [0.0, 0.0, 0.0]),
+        (key(1, "H1"), [0.96, 0.0, 0.0]),
+        (key(1, "H2"), [-0.24, 0.93, 0.0]),
+        (key(2, "O"), [5.0, 0.0, 0.0]),
+    ];
+
+    let mut nh = Neighborhood::new();
+    nh.update(points.clone());
+    assert_eq!(nh.npoints(), 4);
+    assert_eq!(nh.get_position(key(2, "O")), Some(&[5.0, 0.0, 0.0]));
+
+    let o = key(1, "O");
+    let mut found: Vec<_> = nh.neighbors(&o, 1.2).map(|m| m.node).collect();
+    found.sort();
+    assert_eq!(found, vec![key(1, "H1"), key(1, "H2")]);
+    assert_eq!(nh.nearest_neighbors(&o, 1)[0].node, key(1, "H1"));
+    assert_eq!(nh.all_pairs(1.2).count(), 2);
+
+    let nl = nh.neighbor_list(1.2);
+    assert_eq!(nl.get(&o).unwrap().len(), 2);
+    let csr = nl.to_csr();
+    assert_eq!(csr.key(0), Some(&o));
+    assert_eq!(csr.row_index(key(2, "O")), Some(3));
+
+    // periodic images of keyed points
+    nh.set_lattice([[6.0, 0.0, 0.0], [0.0, 6.0, 0.0], [0.0, 0.0, 6.0]]);
+    let m = nh.neighbors(&o, 1.2).find(|m| m.node.0 == 2).unwrap();
+    assert_eq!(m.image.unwrap().0, [-1, 0, 0]);
+    let mic = nh.minimum_image(&o, key(2, "O")).unwrap();
+    assert!((mic.distance - 1.0).abs() < 1e-8);
+
+    assert_eq!(nh.remove(key(1, "H2")), Some([-0.24, 0.93, 0.0]));
+    nh.retain(|(res, _), _| *res == 1);
+    assert_eq!(nh.npoints(), 2);
+}
+
+#[test]
+fn test_bonding_string_keys() {
+    let atoms = vec![
+        ("C1".to_string(), "C", [0.0, 0.0, 0.0]),
+        ("C2".to_string(), "C", [1.54, 0.0, 0.0]),
+        ("Cl".to_string(), "Cl", [-1.77, 0.0, 0.0]),
+    ];
+    let mut bonding = Bonding::new();
+    bonding.update(atoms).unwrap();
+    assert_eq!(bonding.get_element("Cl".to_string()), Some(17));
+    assert_eq!(bonding.bonded("C1".to_string()).count(), 2);
+    assert_eq!(bonding.bonds().len(), 2);
+}
+// e3a9c5d1 ends here
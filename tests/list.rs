@@ -0,0 +1,108 @@
+// [[file:../neighbors.note::4f6a2d8c][4f6a2d8c]]
+use gchemol_neighbors::{Image, Neighborhood};
+
+mod common;
+use common::{frac_to_cart, lcg, neighbor_summary};
+
+fn summary(nl: &gchemol_neighbors::NeighborList) -> Vec<(usize, usize, Option<Image>, u64)> {
+    nl.iter()
+        .flat_map(|(n, row)| row.iter().map(move |m| (*n, m.node, m.image, m.distance.to_bits())))
+        .collect()
+}
+
+#[test]
+fn test_neighbor_list() {
+    let cell = [[9.0, 0.0, 0.0], [1.5, 9.0, 0.0], [-2.0, 0.5, 9.0]];
+    let mut seed = 7;
+    // keys in random order
+    let points: Vec<_> = (0..300)
+        .map(|i| ((i * 37) % 301, [0, 1, 2].map(|_| lcg(&mut seed) * 9.0)))
+        .collect();
+
+    for periodic in [false, true] {
+        let mut nh = Neighborhood::new();
+        nh.update(points.iter().copied());
+        if periodic {
+            nh.set_lattice(cell);
+        }
+        let nl = nh.neighbor_list(3.0);
+        assert_eq!(nl.len(), points.len());
+        assert!(nl.nodes().eq(points.iter().map(|x| &x.0)));
+
+        let mut total = 0;
+        for &(n, _) in points.iter() {
+            let row = nl.get(n).unwrap();
+            assert_eq!(neighbor_summary(row.iter().cloned()), neighbor_summary(nh.neighbors(n, 3.0)));
+            assert!(row.windows(2).all(|w| w[0].distance <= w[1].distance));
+            total += row.len();
+        }
+        assert_eq!(total, nl.num_neighbors());
+        assert!(nl.get(301).is_none());
+
+        // deterministic regardless of the number of threads
+        let expected = summary(&nl);
+        for nthreads in [1, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build().unwrap();
+            let nl = pool.install(|| nh.neighbor_list(3.0));
+            assert_eq!(summary(&nl), expected);
+        }
+    }
+
+    assert!(Neighborhood::<usize>::new().neighbor_list(1.0).is_empty());
+}
+
+#[test]
+fn test_csr_neighbor_list() {
+    let cell = [[5.0, 0.0, 0.0], [0.0, 6.0, 0.0], [1.0, 0.0, 7.0]];
+    let mut seed = 9;
+    let points: Vec<_> = (0..50)
+        .map(|i| (100 - i, [0, 1, 2].map(|_| lcg(&mut seed) * 6.0)))
+        .collect();
+
+    for periodic in [false, true] {
+        let mut nh = Neighborhood::new();
+        nh.update(points.iter().copied());
+        if periodic {
+            nh.set_lattice(cell);
+        }
+        let nl = nh.neighbor_list(2.5);
+        let csr = nl.to_csr();
+        assert_eq!(csr.nrows(), points.len());
+        assert_eq!(csr.nnz(), nl.num_neighbors());
+        assert_eq!(csr.offsets().len(), csr.nrows() + 1);
+        assert_eq!(csr.offsets()[csr.nrows()], csr.nnz());
+
+        for (i, &(n, p)) in points.iter().enumerate() {
+            assert_eq!(csr.key(i), Some(&n));
+            assert_eq!(csr.row_index(n), Some(i));
+            let row = nl.get(n).unwrap();
+            let range = csr.offsets()[i]..csr.offsets()[i + 1];
+            assert_eq!(range.len(), row.len());
+            for (k, m) in range.zip(row) {
+                let j = csr.indices()[k];
+                assert_eq!(csr.key(j), Some(&m.node));
+                assert_eq!(csr.distances()[k], m.distance);
+                assert_eq!(csr.images()[k], m.image.unwrap_or(Image::ORIGIN));
+                // displacement vector to neighbor image
+                let q = nh.get_position(m.node).unwrap();
+                let shift = if periodic { frac_to_cart(cell, csr.images()[k].to_vector().into()) } else { [0.0; 3] };
+                for x in 0..3 {
+                    assert!((csr.vectors()[k][x] - (q[x] + shift[x] - p[x])).abs() < 1e-8);
+                }
+            }
+        }
+
+        // edge index in COO format
+        let [sources, targets] = csr.to_coo();
+        assert_eq!(sources.len(), csr.nnz());
+        assert_eq!(targets, csr.indices());
+        for (k, &i) in sources.iter().enumerate() {
+            assert!(csr.offsets()[i] <= k && k < csr.offsets()[i + 1]);
+        }
+    }
+
+    let csr = Neighborhood::<usize>::new().neighbor_list(1.0).to_csr();
+    assert_eq!(csr.nrows(), 0);
+    assert_eq!(csr.offsets(), &[0]);
+}
+// 4f6a2d8c ends here
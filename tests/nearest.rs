@@ -0,0 +1,88 @@
+// [[file:../neighbors.note::e3a91c57][e3a91c57]]
+use gchemol_neighbors::{Error, Neighborhood};
+
+mod common;
+use common::lcg;
+
+#[test]
+fn test_nearest_fcc() {
+    // primitive cell of fcc Cu
+    let a = 3.61;
+    let cell = [[0.0, a / 2.0, a / 2.0], [a / 2.0, 0.0, a / 2.0], [a / 2.0, a / 2.0, 0.0]];
+    let mut nh = Neighborhood::new();
+    nh.update(vec![(0, [0.0; 3])]);
+    nh.set_lattice(cell);
+
+    let d1 = a / 2f64.sqrt();
+    let neighbors = nh.nearest_neighbors(0, 12);
+    assert_eq!(neighbors.len(), 12);
+    assert!(neighbors.iter().all(|n| n.node == 0 && (n.distance - d1).abs() < 1e-6));
+    let mut images: Vec<_> = neighbors.iter().map(|n| n.image.unwrap()).collect();
+    images.sort();
+    images.dedup();
+    assert_eq!(images.len(), 12);
+
+    // the second shell: 6 neighbors at distance a
+    let neighbors = nh.nearest_neighbors(0, 18);
+    assert_eq!(neighbors.len(), 18);
+    assert!((neighbors[17].distance - a).abs() < 1e-6);
+    assert!(neighbors.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+    // the host itself is included when searching by point
+    let neighbors = nh.nearest([0.0; 3], 1);
+    assert_eq!(neighbors[0].distance, 0.0);
+}
+
+#[test]
+fn test_nearest_aperiodic() {
+    let mut seed = 23;
+    let points: Vec<_> = (0..300)
+        .map(|_| [lcg(&mut seed) * 12.0, lcg(&mut seed) * 12.0, lcg(&mut seed) * 12.0])
+        .collect();
+    let mut nh = Neighborhood::new();
+    nh.update(points.iter().copied().enumerate());
+    // move a few points
+    nh.update(vec![(3, [1.0, 2.0, 3.0]), (7, [1.1, 2.1, 3.1])]);
+
+    let q = [1.0, 2.0, 2.9];
+    let found: Vec<_> = nh.nearest(q, 4).iter().map(|n| n.node).collect();
+    assert_eq!(found[..2], [3, 7]);
+
+    for i in 0..20 {
+        let pi = nh.get_position(i).copied().unwrap();
+        let mut expected: Vec<_> = (0..points.len())
+            .filter(|&j| j != i)
+            .map(|j| {
+                let pj = nh.get_position(j).unwrap();
+                let d2: f64 = (0..3).map(|k| (pi[k] - pj[k]).powi(2)).sum();
+                (d2.sqrt(), j)
+            })
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let expected: Vec<_> = expected[..10].iter().map(|x| x.1).collect();
+        let found: Vec<_> = nh.nearest_neighbors(i, 10).iter().map(|n| n.node).collect();
+        assert_eq!(expected, found);
+    }
+
+    // k exceeds the number of points
+    assert_eq!(nh.nearest(q, 1000).len(), 300);
+}
+
+#[test]
+fn test_nearest_invalid_point() {
+    let mut nh = Neighborhood::new();
+    assert_eq!(nh.try_nearest([0.0; 3], 1).err(), Some(Error::EmptyNeighborhood));
+    nh.update([(0, [0.5; 3]), (1, [1.5, 0.5, 0.5])]);
+    for pbc in [None, Some([true; 3]), Some([false; 3])] {
+        if let Some(pbc) = pbc {
+            nh.set_lattice([[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 3.0]]);
+            nh.set_periodicity(pbc);
+        }
+        for pt in [[f64::NAN, 1.0, 1.0], [f64::INFINITY, 0.0, 0.0]] {
+            assert!(nh.nearest(pt, 1).is_empty());
+            assert!(matches!(nh.try_nearest(pt, 1), Err(Error::NonFiniteCoordinates(_))));
+        }
+        assert_eq!(nh.try_nearest([0.0; 3], 1).unwrap()[0].node, 0);
+    }
+}
+// e3a91c57 ends here
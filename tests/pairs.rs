@@ -0,0 +1,55 @@
+// [[file:../neighbors.note::3d8b6f41][3d8b6f41]]
+use gchemol_neighbors::{Image, Neighborhood};
+
+#[test]
+fn test_all_pairs() {
+    #[rustfmt::skip]
+    let particles = vec![
+        [ 0.        ,  0.        , 23.78162099],
+        [ 1.24405   ,  1.24405   , 25.47342157],
+        [ 0.        ,  0.        , 20.24425178],
+        [ 1.24405   ,  1.24405   , 21.99516685],
+        [ 0.        ,  0.        , 16.71356813],
+        [ 1.24405   ,  1.24405   , 18.47363187],
+        [ 0.        ,  0.        , 13.19203315],
+        [ 1.24405   ,  1.24405   , 14.94294822],
+        [ 0.        ,  0.        ,  9.71377843],
+        [ 1.24405   ,  1.24405   , 11.40557901]
+    ];
+
+    let cell = [[2.4881, 0.0, 0.0], [0.0, 2.4881, 0.0], [0.0, 0.0, 35.1872]];
+    let mut nh = Neighborhood::new();
+    nh.update(particles.iter().enumerate().map(|(i, &v)| (i, v)));
+    nh.set_lattice(cell);
+
+    // node 0 has four self images within 3.0, forming two pairs
+    let pairs: Vec<_> = nh.all_pairs(3.0).filter(|(i, m)| *i == 0 && m.node == 0).collect();
+    assert_eq!(pairs.len(), 2);
+    let mut images: Vec<_> = pairs.iter().map(|(_, m)| m.image.unwrap()).collect();
+    images.sort();
+    assert_eq!(images, vec![Image::new(0, 1, 0), Image::new(1, 0, 0)]);
+
+    for cutoff in [3.0, 4.0, 5.0] {
+        let nfull: usize = (0..10).map(|i| nh.neighbors(i, cutoff).count()).sum();
+        let mut pairs: Vec<_> = nh
+            .all_pairs(cutoff)
+            .map(|(i, m)| (i, m.node, m.image.unwrap()))
+            .collect();
+        assert_eq!(pairs.len() * 2, nfull);
+
+        // no pair will be visited twice
+        let reversed: Vec<_> = pairs.iter().map(|&(i, j, image)| (j, i, -image)).collect();
+        pairs.extend(reversed);
+        pairs.sort();
+        pairs.dedup();
+        assert_eq!(pairs.len(), nfull);
+    }
+
+    // aperiodic system
+    let mut nh = Neighborhood::new();
+    nh.update(particles.iter().enumerate().map(|(i, &v)| (i, v)));
+    let nfull: usize = (0..10).map(|i| nh.neighbors(i, 2.5).count()).sum();
+    assert_eq!(nh.all_pairs(2.5).count() * 2, nfull);
+    assert!(nh.all_pairs(2.5).all(|(i, m)| i < m.node));
+}
+// 3d8b6f41 ends here
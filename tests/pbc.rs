@@ -0,0 +1,92 @@
+// [[file:../neighbors.note::3c8d1a5e][3c8d1a5e]]
+use gchemol_neighbors::Neighborhood;
+
+mod common;
+use common::BACKENDS;
+
+fn images(nh: &Neighborhood, n: usize, cutoff: f64) -> Vec<(usize, [i32; 3])> {
+    let mut items: Vec<_> = nh.neighbors(n, cutoff).map(|m| (m.node, m.image.unwrap().0)).collect();
+    items.sort();
+    items
+}
+
+#[test]
+fn test_slab() {
+    // a square lattice with two layers, periodic along a and b only
+    let cell = [[2.5, 0.0, 0.0], [0.0, 2.5, 0.0], [0.0, 0.0, 3.0]];
+    let points = [[0.0, 0.0, 0.5], [1.25, 1.25, 2.5], [0.0, 0.0, 10.5]];
+
+    for backend in BACKENDS {
+        let mut nh = Neighborhood::new();
+        nh.set_backend(backend);
+        nh.update(points[..2].iter().copied().enumerate());
+        nh.set_lattice(cell);
+        assert_eq!(nh.periodicity(), [true; 3]);
+        assert_eq!(nh.neighbors(0, 3.1).count(), 14, "{backend:?}");
+
+        nh.set_periodicity([true, true, false]);
+        nh.update([(2, points[2])]);
+        assert_eq!(nh.periodicity(), [true, true, false]);
+        let expected = vec![
+            (0, [-1, 0, 0]),
+            (0, [0, -1, 0]),
+            (0, [0, 1, 0]),
+            (0, [1, 0, 0]),
+            (1, [-1, -1, 0]),
+            (1, [-1, 0, 0]),
+            (1, [0, -1, 0]),
+            (1, [0, 0, 0]),
+        ];
+        assert_eq!(images(&nh, 0, 3.1), expected, "{backend:?}");
+
+        // the isolated atom far away in vacuum only sees its in-plane images
+        let expected = vec![(2, [-1, 0, 0]), (2, [0, -1, 0]), (2, [0, 1, 0]), (2, [1, 0, 0])];
+        assert_eq!(images(&nh, 2, 3.1), expected, "{backend:?}");
+
+        // moved points
+        nh.update([(2, [0.0, 0.0, -9.5])]);
+        assert_eq!(images(&nh, 2, 3.1), expected, "{backend:?}");
+
+        let d = (2.0 * 1.25f64.powi(2) + 4.0).sqrt();
+        let mic = nh.minimum_image(0, 1).unwrap();
+        assert!((mic.distance - d).abs() < 1e-8);
+        assert_eq!(mic.image.unwrap()[2], 0);
+    }
+}
+
+#[test]
+fn test_chain() {
+    // a 1D chain along c
+    let cell = [[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 2.5]];
+    let points = [[0.0, 0.0, 0.0]];
+
+    for backend in BACKENDS {
+        let mut nh = Neighborhood::new();
+        nh.set_backend(backend);
+        nh.update(points.iter().copied().enumerate());
+        nh.set_lattice(cell);
+        assert_eq!(nh.neighbors(0, 3.1).count(), 6, "{backend:?}");
+
+        nh.set_periodicity([false, false, true]);
+        let expected = vec![(0, [0, 0, -1]), (0, [0, 0, 1])];
+        assert_eq!(images(&nh, 0, 3.1), expected, "{backend:?}");
+        assert_eq!(nh.neighbors(0, 5.1).count(), 4, "{backend:?}");
+
+        let distances: Vec<_> = nh.nearest_neighbors(0, 4).iter().map(|m| m.distance).collect();
+        assert_eq!(distances, vec![2.5, 2.5, 5.0, 5.0], "{backend:?}");
+
+        // no periodic images at all
+        nh.set_periodicity([false; 3]);
+        assert_eq!(nh.neighbors(0, 10.0).count(), 0, "{backend:?}");
+        assert_eq!(nh.nearest([1.0, 1.0, 1.0], 3).len(), 1, "{backend:?}");
+
+        // periodicity is kept after clearing data
+        nh.set_periodicity([false, false, true]);
+        nh.clear();
+        assert_eq!(nh.periodicity(), [false, false, true]);
+        nh.update(points.iter().copied().enumerate());
+        nh.set_lattice(cell);
+        assert_eq!(images(&nh, 0, 3.1), vec![(0, [0, 0, -1]), (0, [0, 0, 1])], "{backend:?}");
+    }
+}
+// 3c8d1a5e ends here
@@ -1,10 +1,8 @@
 // [[file:../neighbors.note::ef6b0d05][ef6b0d05]]
-use gchemol_neighbors::Neighborhood;
+use gchemol_neighbors::{Image, Neighborhood};
 
 #[test]
 fn test_periodic_neighbors() {
-    use approx::*;
-    use vecfx::*;
 
     let particles = [[ 0.60421912,  4.2840792 ,  0.67433509],
                      [-0.69258171,  3.9731936 ,  3.49208748],
@@ -40,17 +38,13 @@ fn test_periodic_neighbors() {
     assert_eq!(nodes, vec![7, 8, 9, 14]);
 
     let images: Vec<_> = neighbors.iter().map(|n| n.image.unwrap()).collect();
-    let expected = vec![
-        [0.0, 0.0, 0.0],   // node 7
-        [0.0, 1.0, 0.0],   // node 8
-        [0.0, 0.0, 0.0],   // node 9
-        [-1.0, 0.0, -1.0], // node 14
+    let expected = [
+        Image::new(0, 0, 0),   // node 7
+        Image::new(0, 1, 0),   // node 8
+        Image::new(0, 0, 0),   // node 9
+        Image::new(-1, 0, -1), // node 14
     ];
-
-    for (i, &image) in images.iter().enumerate() {
-        let _image = Vector3f::from(expected[i]);
-        assert_relative_eq!(image, _image);
-    }
+    assert_eq!(images, expected);
 
     // Fe32C2
     #[rustfmt::skip]
@@ -105,26 +99,135 @@ fn test_periodic_neighbors() {
     let nodes: Vec<_> = neighbors.iter().map(|n| n.node).collect();
     assert_eq!(nodes, vec![3, 5, 12, 12, 19, 20, 23, 24, 27, 28, 31, 32]);
 
-    let images: Vec<_> = neighbors.iter().map(|n| n.image.unwrap()).collect();
+    let images: Vec<_> = neighbors.iter().map(|n| n.image.unwrap().0).collect();
     let expected = vec![
-        [0.0, 0.0, 0.0],   // node 3
-        [0.0, 0.0, 0.0],   // node 5
-        [0.0, 0.0, 0.0],   // node 12
-        [-1.0, 0.0, 0.0],  // node 12
-        [0.0, 0.0, 0.0],   // node 19
-        [0.0, 0.0, 0.0],   // node 20
-        [0.0, -1.0, 0.0],  // node 23
-        [0.0, -1.0, 0.0],  // node 24
-        [-1.0, 0.0, 0.0],  // node 27
-        [-1.0, 0.0, 0.0],  // node 28
-        [-1.0, -1.0, 0.0], // node 31
-        [-1.0, -1.0, 0.0], // node 32
+        [0, 0, 0],   // node 3
+        [0, 0, 0],   // node 5
+        [0, 0, 0],   // node 12
+        [-1, 0, 0],  // node 12
+        [0, 0, 0],   // node 19
+        [0, 0, 0],   // node 20
+        [0, -1, 0],  // node 23
+        [0, -1, 0],  // node 24
+        [-1, 0, 0],  // node 27
+        [-1, 0, 0],  // node 28
+        [-1, -1, 0], // node 31
+        [-1, -1, 0], // node 32
     ];
+    assert_eq!(images, expected);
+
+}
+// ef6b0d05 ends here
 
-    for (&image, image_) in images.iter().zip(expected) {
-        let image_ = Vector3f::from(image_);
-        assert_relative_eq!(image, image_);
+// [[file:../neighbors.note::1a7e4c02][1a7e4c02]]
+#[test]
+fn test_minimum_image() {
+    use vecfx::*;
+
+    #[rustfmt::skip]
+    let particles = [[ 0.60421912,  4.2840792 ,  0.67433509],
+                     [-0.69258171,  3.9731936 ,  3.49208748],
+                     [ 0.32811792,  4.34729737,  6.48343793],
+                     [ 4.88477572,  1.81537674,  6.26972558],
+                     [ 6.14499816,  1.48505734,  3.37312786],
+                     [ 5.12754047,  1.85762907,  0.43572421],
+                     [ 2.09507387,  3.66872721,  0.39353504],
+                     [ 0.5848138 ,  0.91854645,  0.28564143],
+                     [ 0.33364169,  4.10698461,  2.26790994],
+                     [-1.14582521,  2.41879964,  3.57784907],
+                     [ 0.06571752,  4.4286596 ,  4.80486228],
+                     [ 3.78132323,  0.96146537,  0.19503846],
+                     [ 3.29078661,  1.21859679,  6.60654731],
+                     [ 4.93953611,  3.49170736,  6.71444093],
+                     [ 5.15070623,  1.63464631,  4.60290757],
+                     [ 6.60903043,  4.89706872,  3.2209702 ],
+                     [ 5.36681478,  1.95057166,  2.05143108],
+                     [ 1.73241622,  3.38087446,  6.78291188]];
+
+    #[rustfmt::skip]
+    let cell = [[ 8.60700000e+00,  0.00000000e+00,  0.00000000e+00],
+                [ 8.64636107e-04,  4.95399992e+00,  0.00000000e+00],
+                [-3.14318359e+00,  1.38078488e-02,  6.91625732e+00]];
+    let mut nh = Neighborhood::new();
+    nh.update(particles.iter().enumerate().map(|(i, &v)| (i, v)));
+    nh.set_lattice(cell);
+
+    let [va, vb, vc] = cell.map(Vector3f::from);
+    for i in 0..particles.len() {
+        for j in 0..particles.len() {
+            let pi = Vector3f::from(particles[i]);
+            let pj = Vector3f::from(particles[j]);
+            // brute force search over images
+            let mut dmin = f64::MAX;
+            for a in -3..=3 {
+                for b in -3..=3 {
+                    for c in -3..=3 {
+                        let d = (pj + a as f64 * va + b as f64 * vb + c as f64 * vc - pi).norm();
+                        dmin = dmin.min(d);
+                    }
+                }
+            }
+            let mic = nh.minimum_image(i, j).unwrap();
+            assert!((mic.distance - dmin).abs() < 1e-8, "{i}-{j}: {} vs {dmin}", mic.distance);
+            assert_eq!(nh.distance(i, j), Some(mic.distance));
+            let image = mic.image.unwrap().to_vector();
+            let expected = pj + image[0] * va + image[1] * vb + image[2] * vc - pi;
+            assert!((mic.vector - expected).norm() < 1e-8);
+        }
     }
+    assert!(nh.minimum_image(0, 100).is_none());
 
+    // the closest atom to a point
+    let pt = [0.4, 4.2, 1.0];
+    let closest = nh.closest(pt).unwrap();
+    assert_eq!(closest.node, 0);
+    assert!((closest.distance - nh.nearest(pt, 1)[0].distance).abs() < 1e-8);
 }
-// ef6b0d05 ends here
+// 1a7e4c02 ends here
+
+// [[file:../neighbors.note::7d2f5b91][7d2f5b91]]
+#[test]
+fn test_neighbor_vectors() {
+    use gchemol_neighbors::Backend;
+    use vecfx::*;
+
+    #[rustfmt::skip]
+    let particles = [[ 0.60421912,  4.2840792 ,  0.67433509],
+                     [-0.69258171,  3.9731936 ,  3.49208748],
+                     [ 0.32811792,  4.34729737,  6.48343793],
+                     [ 4.88477572,  1.81537674,  6.26972558],
+                     [ 6.14499816,  1.48505734,  3.37312786],
+                     [ 5.12754047,  1.85762907,  0.43572421],
+                     [ 2.09507387,  3.66872721,  0.39353504],
+                     [ 0.5848138 ,  0.91854645,  0.28564143]];
+
+    #[rustfmt::skip]
+    let cell = [[ 8.60700000e+00,  0.00000000e+00,  0.00000000e+00],
+                [ 8.64636107e-04,  4.95399992e+00,  0.00000000e+00],
+                [-3.14318359e+00,  1.38078488e-02,  6.91625732e+00]];
+    let [va, vb, vc] = cell.map(Vector3f::from);
+
+    for backend in [Backend::Octree, Backend::KdTree, Backend::CellList, Backend::BruteForce] {
+        let mut nh = Neighborhood::new();
+        nh.set_backend(backend);
+        nh.update(particles.iter().copied().enumerate());
+        for periodic in [false, true] {
+            if periodic {
+                nh.set_lattice(cell);
+            }
+            for (i, &p) in particles.iter().enumerate() {
+                let pi = Vector3f::from(p);
+                for m in nh.neighbors(i, 4.0) {
+                    let mut expected = Vector3f::from(particles[m.node]);
+                    if let Some(image) = m.image.map(|x| x.to_vector()) {
+                        expected += image[0] * va + image[1] * vb + image[2] * vc;
+                    }
+                    assert!((Vector3f::from(m.position) - expected).norm() < 1e-8, "{backend:?}");
+                    assert!((m.vector - (expected - pi)).norm() < 1e-8, "{backend:?}");
+                    assert!((m.vector.norm() - m.distance).abs() < 1e-8, "{backend:?}");
+                }
+            }
+        }
+    }
+}
+// 7d2f5b91 ends here
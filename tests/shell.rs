@@ -0,0 +1,118 @@
+// [[file:../neighbors.note::5c8e1b07][5c8e1b07]]
+use gchemol_neighbors::{Image, Neighborhood};
+use vecfx::*;
+
+mod common;
+use common::{lcg, neighbor_summary, BACKENDS};
+
+/// All images of `points` with distances to `pt` in range `rmin..rmax` by
+/// brute force, excluding image `exclude`.
+fn shell_by_brute_force(
+    points: &[[f64; 3]],
+    cell: Option<([[f64; 3]; 3], [bool; 3])>,
+    pt: [f64; 3],
+    (rmin, rmax): (f64, f64),
+    exclude: Option<usize>,
+) -> Vec<(usize, Option<Image>, i64)> {
+    let pt = Vector3f::from(pt);
+    let mut found = vec![];
+    for (j, &p) in points.iter().enumerate() {
+        let p = Vector3f::from(p);
+        let Some((cell, pbc)) = cell else {
+            let d = (p - pt).norm();
+            if d < rmax && d >= rmin && exclude != Some(j) {
+                found.push((j, None, (d * 1e6).round() as i64));
+            }
+            continue;
+        };
+        // all lattice translations shorter than `|p - pt| + rmax`
+        let [va, vb, vc] = cell.map(Vector3f::from);
+        let volume = va.dot(&vb.cross(&vc)).abs();
+        let widths = [vb.cross(&vc), vc.cross(&va), va.cross(&vb)].map(|x| volume / x.norm());
+        let [ra, rb, rc] = [0, 1, 2].map(|x| {
+            let n = if pbc[x] { ((p - pt).norm() + rmax) / widths[x] } else { 0.0 };
+            -(n.ceil() as i32)..=n.ceil() as i32
+        });
+        for a in ra {
+            for b in rb.clone() {
+                for c in rc.clone() {
+                    let image = Image::new(a, b, c);
+                    let d = (p + a as f64 * va + b as f64 * vb + c as f64 * vc - pt).norm();
+                    if d < rmax && d >= rmin && !(exclude == Some(j) && image.is_origin()) {
+                        found.push((j, Some(image), (d * 1e6).round() as i64));
+                    }
+                }
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+#[test]
+fn test_search_shell() {
+    let cell = [[9.0, 0.0, 0.0], [2.0, 8.0, 0.0], [-1.0, 1.0, 10.0]];
+    let mut seed = 23;
+    let points: Vec<_> = (0..400).map(|_| [0, 1, 2].map(|_| lcg(&mut seed) * 11.0 - 1.0)).collect();
+    // some points moved out of the spatial index
+    let moved: Vec<_> = (0..10).map(|i| (i * 7, [i as f64; 3])).collect();
+    let mut current = points.clone();
+    for &(i, p) in &moved {
+        current[i] = p;
+    }
+    let shells = [(0.0, 2.0), (2.0, 4.5), (3.0, 3.5), (5.0, 12.0)];
+    let probes = [[0.5, 0.5, 0.5], [4.0, 3.0, 5.0], [12.0, -3.0, 6.0]];
+    let hosts = [0, 7, 100];
+
+    for pbc in [None, Some([true; 3]), Some([false, true, true])] {
+        // the reference shared by all backends
+        let reference = |pt, shell, exclude| shell_by_brute_force(&current, pbc.map(|pbc| (cell, pbc)), pt, shell, exclude);
+        let expected: Vec<_> = shells
+            .iter()
+            .map(|&shell| {
+                let found: Vec<_> = probes.iter().map(|&pt| reference(pt, shell, None)).collect();
+                let neighbors: Vec<_> = hosts.iter().map(|&n| reference(current[n], shell, Some(n))).collect();
+                (found, neighbors)
+            })
+            .collect();
+
+        for backend in BACKENDS {
+            let mut nh = Neighborhood::new();
+            nh.set_backend(backend);
+            nh.update(points.iter().copied().enumerate());
+            if let Some(pbc) = pbc {
+                nh.set_lattice(cell);
+                nh.set_periodicity(pbc);
+            }
+            nh.update(moved.iter().copied());
+
+            for ((rmin, rmax), (found, neighbors)) in shells.into_iter().zip(&expected) {
+                for (&pt, expected) in probes.iter().zip(found) {
+                    assert_eq!(&neighbor_summary(nh.search_shell(pt, rmin, rmax)), expected, "{backend:?}");
+                }
+                for (&n, expected) in hosts.iter().zip(neighbors) {
+                    assert_eq!(&neighbor_summary(nh.neighbors_in_shell(n, rmin, rmax)), expected, "{backend:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fcc_second_shell() {
+    // primitive cell of fcc Cu: 6 atoms in the second coordination shell
+    let a = 3.61;
+    let cell = [[0.0, a / 2.0, a / 2.0], [a / 2.0, 0.0, a / 2.0], [a / 2.0, a / 2.0, 0.0]];
+    for backend in BACKENDS {
+        let mut nh = Neighborhood::new();
+        nh.set_backend(backend);
+        nh.update([(0, [0.0; 3])]);
+        nh.set_lattice(cell);
+        assert_eq!(nh.neighbors_in_shell(0, 2.6, 3.7).count(), 6, "{backend:?}");
+        assert_eq!(nh.neighbors_in_shell(0, 0.0, 3.7).count(), 18, "{backend:?}");
+        assert_eq!(nh.neighbors_in_shell(0, 2.6, 2.6).count(), 0, "{backend:?}");
+        // the host itself is in the inner sphere
+        assert_eq!(nh.search_shell([0.0; 3], 0.0, 2.6).count(), 13, "{backend:?}");
+    }
+}
+// 5c8e1b07 ends here
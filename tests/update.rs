@@ -1,6 +1,9 @@
 // [[file:../neighbors.note::fd1808b2][fd1808b2]]
 use gchemol_neighbors::Neighborhood;
 
+mod common;
+use common::lcg;
+
 #[test]
 fn test_neighbors() {
     let positions = vec![[-1.5365e+00,  2.4770e-01,  0.0000e+00],
@@ -56,3 +59,101 @@ fn test_neighbors() {
     assert_eq!(n, 2);     // point 9 + 8
 }
 // fd1808b2 ends here
+
+// [[file:../neighbors.note::0c6e2d9b][0c6e2d9b]]
+#[test]
+fn test_incremental_update() {
+    let mut seed = 7;
+    let mut positions: Vec<_> = (0..500)
+        .map(|_| [lcg(&mut seed) * 20.0, lcg(&mut seed) * 20.0, lcg(&mut seed) * 20.0])
+        .collect();
+
+    let mut nh = Neighborhood::new();
+    nh.update(positions.iter().copied().enumerate());
+
+    let brute_force = |positions: &[[f64; 3]], i: usize, r: f64| {
+        let pi = positions[i];
+        let mut nodes: Vec<_> = (0..positions.len())
+            .filter(|&j| {
+                let pj = positions[j];
+                let d2: f64 = (0..3).map(|k| (pi[k] - pj[k]).powi(2)).sum();
+                j != i && d2 < r * r
+            })
+            .collect();
+        nodes.sort();
+        nodes
+    };
+
+    // move a few points in each step, and insert a new one; the spatial index
+    // will be rebuilt after many steps.
+    for step in 0..100 {
+        let mut moved = vec![];
+        for _ in 0..3 {
+            let i = (lcg(&mut seed) * positions.len() as f64) as usize;
+            positions[i] = [lcg(&mut seed) * 20.0, lcg(&mut seed) * 20.0, lcg(&mut seed) * 20.0];
+            moved.push((i, positions[i]));
+        }
+        if step % 10 == 0 {
+            positions.push([lcg(&mut seed) * 20.0, lcg(&mut seed) * 20.0, lcg(&mut seed) * 20.0]);
+            moved.push((positions.len() - 1, *positions.last().unwrap()));
+        }
+        nh.update(moved.iter().copied());
+        assert_eq!(nh.npoints(), positions.len());
+
+        for &(i, _) in moved.iter() {
+            let mut nodes: Vec<_> = nh.neighbors(i, 3.0).map(|n| n.node).collect();
+            nodes.sort();
+            assert_eq!(nodes, brute_force(&positions, i, 3.0));
+        }
+    }
+}
+// 0c6e2d9b ends here
+
+// [[file:../neighbors.note::4f1a8c3e][4f1a8c3e]]
+#[test]
+fn test_remove_points() {
+    let mut seed = 11;
+    let positions: Vec<_> = (0..300)
+        .map(|_| [lcg(&mut seed) * 15.0, lcg(&mut seed) * 15.0, lcg(&mut seed) * 15.0])
+        .collect();
+
+    let mut nh = Neighborhood::new();
+    nh.update(positions.iter().copied().enumerate());
+
+    assert_eq!(nh.remove(7), Some(positions[7]));
+    assert_eq!(nh.remove(7), None);
+    assert_eq!(nh.npoints(), 299);
+    assert_eq!(nh.remove_many(vec![1, 2, 3, 7, 1000]), 3);
+    assert_eq!(nh.npoints(), 296);
+    // remove all points with odd keys
+    nh.retain(|k, _| k % 2 == 0);
+    assert_eq!(nh.npoints(), 149);
+    assert!(nh.get_position(9).is_none());
+
+    // check against brute force results
+    for i in (0..300).step_by(2).filter(|&i| i != 2) {
+        let pi = positions[i];
+        let mut expected: Vec<_> = (0..300)
+            .step_by(2)
+            .filter(|&j| {
+                let pj = positions[j];
+                let d2: f64 = (0..3).map(|k| (pi[k] - pj[k]).powi(2)).sum();
+                j != 2 && j != i && d2 < 9.0
+            })
+            .collect();
+        expected.sort();
+        let mut nodes: Vec<_> = nh.neighbors(i, 3.0).map(|n| n.node).collect();
+        nodes.sort();
+        assert_eq!(nodes, expected);
+    }
+
+    // moving a removed point back
+    nh.update(vec![(7, positions[7])]);
+    assert_eq!(nh.npoints(), 150);
+    assert!(nh.search(positions[7], 0.1).any(|n| n.node == 7));
+
+    nh.clear();
+    assert_eq!(nh.npoints(), 0);
+    assert!(nh.try_search(positions[7], 1.0).is_err());
+}
+// 4f1a8c3e ends here
@@ -0,0 +1,64 @@
+// [[file:../neighbors.note::7b3f5e10][7b3f5e10]]
+use gchemol_neighbors::{Neighborhood, VerletList};
+
+mod common;
+use common::{lcg, neighbor_summary};
+
+fn check_verlet_list(periodic: bool) {
+    let mut seed = 3;
+    let a = 12.0;
+    let mut positions: Vec<_> = (0..200)
+        .map(|_| [lcg(&mut seed) * a, lcg(&mut seed) * a, lcg(&mut seed) * a])
+        .collect();
+
+    let mut nh = Neighborhood::new();
+    nh.update(positions.iter().copied().enumerate());
+    let cell = [[a, 0.0, 0.0], [0.0, a, 0.0], [0.3, 0.5, a]];
+    if periodic {
+        nh.set_lattice(cell);
+    }
+
+    let cutoff = 2.5;
+    let skin = 0.6;
+    let mut vl = VerletList::new(nh, cutoff, skin);
+
+    let mut nrebuilds = 0;
+    for _ in 0..20 {
+        // small random displacements
+        for p in positions.iter_mut() {
+            for x in p.iter_mut() {
+                *x += (lcg(&mut seed) - 0.5) * 0.1;
+            }
+        }
+        if vl.update(positions.iter().copied().enumerate()) {
+            nrebuilds += 1;
+        }
+
+        // compare with direct search using current positions
+        let mut nh = Neighborhood::new();
+        nh.update(positions.iter().copied().enumerate());
+        if periodic {
+            nh.set_lattice(cell);
+        }
+        for i in 0..positions.len() {
+            assert_eq!(neighbor_summary(vl.neighbors(i)), neighbor_summary(nh.neighbors(i, cutoff)));
+            for m in vl.neighbors(i) {
+                assert!((m.vector.norm() - m.distance).abs() < 1e-8);
+            }
+        }
+    }
+    // maximum displacement per step is less than 0.1 * sqrt(3) / 2
+    assert!(nrebuilds > 0 && nrebuilds < 20, "{nrebuilds}");
+
+    // move a single point far away
+    positions[0][0] += 1.0;
+    assert!(vl.update(vec![(0, positions[0])]));
+    assert!(!vl.update(vec![(0, positions[0])]));
+}
+
+#[test]
+fn test_verlet_list() {
+    check_verlet_list(false);
+    check_verlet_list(true);
+}
+// 7b3f5e10 ends here